@@ -127,10 +127,8 @@ async function loadRemotes() {
     console.log("Loading remotes...");
     showStatus('Loading remotes...', 'info');
 
-    // Get the config path from localStorage or use default
-    const configPath = localStorage.getItem('rcloneConfigPath');
-    const configPathFinal = configPath || null;
-    const remotes = await invoke('get_remotes', { configPathOpt: configPathFinal });
+    // Remotes of the active profile's config
+    const remotes = await invoke('get_remotes', {});
 
     await renderRemotesTable(remotes);
     const message = `Loaded ${remotes.length} remotes`;
@@ -178,7 +176,7 @@ async function renderRemotesTable(remotes) {
   }
 
   // Get available plugins to check secure status
-  const plugins = await invoke('get_available_plugins');
+  const plugins = (await invoke('get_available_plugins')).plugins;
 
   // Process remotes after getting plugins
   remotes.forEach(remote => {
@@ -335,11 +333,9 @@ async function mountSelected() {
       setTimeout(() => reject(new Error('Mount operation timed out after 10 seconds')), 10000);
     });
 
-    // Get the config path from localStorage or use default (null)
-    const configPath = localStorage.getItem('rcloneConfigPath') || null;
     // Race the invoke call with the timeout
     const result = await Promise.race([
-      invoke('mount_remote', { remoteName, configPathOpt: configPath }),
+      invoke('mount_remote', { remoteName }),
       timeoutPromise
     ]);
 
@@ -417,11 +413,9 @@ async function unmountSelected() {
       setTimeout(() => reject(new Error('Unmount operation timed out after 10 seconds')), 10000);
     });
 
-    // Get the config path from localStorage or use default (null)
-    const configPath = localStorage.getItem('rcloneConfigPath') || null;
     // Race the invoke call with the timeout
     const result = await Promise.race([
-      invoke('unmount_remote', { remoteName, configPathOpt: configPath }),
+      invoke('unmount_remote', { remoteName }),
       timeoutPromise
     ]);
 
@@ -521,11 +515,9 @@ async function testSelected() {
       setTimeout(() => reject(new Error('Test connection timed out after 10 seconds')), 10000);
     });
 
-    // Get the config path from localStorage or use default (null)
-    const configPath = localStorage.getItem('rcloneConfigPath') || null;
     // Race the invoke call with the timeout
     const result = await Promise.race([
-      invoke('test_connection', { remoteName, configPathOpt: configPath }),
+      invoke('test_connection', { remoteName }),
       timeoutPromise
     ]);
 
@@ -740,6 +732,24 @@ function resetOperationCancellation() {
 
 // Open settings
 async function openSettings() {
+  // Theme and profiles live in the backend's settings store
+  let settings, profileList;
+  try {
+    [settings, profileList] = await Promise.all([invoke('get_settings', {}), invoke('list_profiles', {})]);
+  } catch (error) {
+    console.error('Error loading settings:', error);
+    showStatus(`Failed to load settings: ${error.message || error}`, 'error');
+    return;
+  }
+
+  const profileOf = name => profileList.profiles.find(profile => profile.name === name);
+  const activeProfile = profileOf(profileList.active) || profileList.profiles[0];
+
+  let profileOptions = '';
+  profileList.profiles.forEach(profile => {
+    profileOptions += `<option value="${profile.name}" ${profile.name === activeProfile.name ? 'selected' : ''}>${profile.name}</option>`;
+  });
+
   // Create modal div with consistent styling
   const modal = document.createElement('div');
   modal.id = 'settings-modal';
@@ -751,10 +761,18 @@ async function openSettings() {
         <span class="progress-title">Settings</span>
       </div>
       <div class="progress-body">
+        <div style="margin: 10px 0;">
+          <label class="cs-input__label">Profile:</label>
+          <div style="margin-top: 4px;">
+            <select id="profile-select" class="cs-select">
+              ${profileOptions}
+            </select>
+          </div>
+        </div>
         <div style="margin: 10px 0;">
           <label class="cs-input__label">Config Path:</label>
           <div style="display: flex; gap: 4px; align-items: center;">
-            <input type="text" id="config-path" class="cs-input" style="flex: 1; margin: 4px 0;" value="${localStorage.getItem('rcloneConfigPath') || ''}" placeholder="~/.config/rclone/rclone.conf">
+            <input type="text" id="config-path" class="cs-input" style="flex: 1; margin: 4px 0;" value="${activeProfile.config_path}" placeholder="~/.config/rclone/rclone.conf">
             <button class="cs-btn" id="browse-config-btn" style="white-space: nowrap;">Browse...</button>
           </div>
         </div>
@@ -762,7 +780,7 @@ async function openSettings() {
           <label class="cs-input__label">Theme:</label>
           <div style="margin-top: 4px;">
             <select id="theme-select" class="cs-select">
-              <option value="cs16" ${localStorage.getItem('theme') === 'cs16' ? 'selected' : ''}>CS 1.6 Steam</option>
+              <option value="cs16" ${settings.theme === 'cs16' ? 'selected' : ''}>CS 1.6 Steam</option>
             </select>
           </div>
         </div>
@@ -776,15 +794,32 @@ async function openSettings() {
 
   document.body.appendChild(modal);
 
+  // Show the config path of the picked profile
+  modal.querySelector('#profile-select').addEventListener('change', event => {
+    document.getElementById('config-path').value = profileOf(event.target.value).config_path;
+  });
+
   // Add event listeners for the buttons
-  modal.querySelector('.settings-modal-ok-btn').addEventListener('click', () => {
-    const newPath = document.getElementById('config-path').value;
-    localStorage.setItem('rcloneConfigPath', newPath);
-    // Save theme selection
-    const themeSelect = document.getElementById('theme-select');
-    if (themeSelect) {
-      const selectedTheme = themeSelect.value;
-      localStorage.setItem('theme', selectedTheme);
+  modal.querySelector('.settings-modal-ok-btn').addEventListener('click', async () => {
+    const profile = profileOf(document.getElementById('profile-select').value);
+    const newPath = document.getElementById('config-path').value.trim();
+    const selectedTheme = document.getElementById('theme-select').value;
+
+    try {
+      // Theme first, update_settings replaces the profiles with the ones loaded above
+      if (selectedTheme !== settings.theme) {
+        await invoke('update_settings', { settings: { ...settings, theme: selectedTheme } });
+      }
+      if (newPath !== profile.config_path) {
+        await invoke('save_profile', { profile: { ...profile, config_path: newPath } });
+      }
+      if (profile.name !== profileList.active) {
+        await invoke('switch_profile', { name: profile.name });
+      }
+    } catch (error) {
+      console.error('Error saving settings:', error);
+      showGeneralModal('Error', `Failed to save settings: ${error.message || error}`);
+      return;
     }
     modal.remove();
 
@@ -820,7 +855,7 @@ async function openSettings() {
 async function addRemote() {
   // First get available plugins to know what types of remotes we can add
   try {
-    const plugins = await invoke('get_available_plugins');
+    const plugins = (await invoke('get_available_plugins')).plugins;
 
     if (plugins.length === 0) {
       showGeneralModal('No Plugins Available', 'No remote plugins available. You can configure remotes using rclone config directly.');
@@ -877,7 +912,7 @@ async function addRemote() {
     // When a remote type is selected, load its specific fields
     document.getElementById('remote-type').addEventListener('change', async function() {
       const selectedPluginName = this.value;
-      const plugins = await invoke('get_available_plugins');
+      const plugins = (await invoke('get_available_plugins')).plugins;
       const selectedPlugin = plugins.find(p => p.name === selectedPluginName);
 
       if (selectedPlugin) {
@@ -990,7 +1025,7 @@ async function addRemote() {
       }
 
       // Get plugin to see what fields are needed
-      const plugins = await invoke('get_available_plugins');
+      const plugins = (await invoke('get_available_plugins')).plugins;
       const plugin = plugins.find(p => p.name === remoteType);
       if (!plugin) {
         showGeneralModal('Error', 'Invalid plugin selected');
@@ -1015,12 +1050,9 @@ async function addRemote() {
       });
 
       try {
-        // Get the config path from localStorage or use default (null)
-        const configPath = localStorage.getItem('rcloneConfigPath') || null;
         const result = await invoke('add_remote_with_plugin', {
           pluginName: remoteType,
-          config: config,
-          config_path_opt: configPath
+          config: config
         });
 
         if (result.success) {
@@ -1029,8 +1061,7 @@ async function addRemote() {
 
           // Now test the connection to the newly added remote
           const testResult = await invoke('test_connection', {
-            remoteName: remoteName,
-            config_path_opt: configPath
+            remoteName: remoteName
           });
 
           if (testResult.success) {
@@ -1197,7 +1228,7 @@ function createContextMenu(x, y, remote) {
 async function handleEditRemote(remote) {
   try {
     // Get available plugins to find one that matches this remote type
-    const plugins = await invoke('get_available_plugins');
+    const plugins = (await invoke('get_available_plugins')).plugins;
 
     // Find a plugin that matches this remote type
     // Look for exact match first (more reliable)
@@ -1214,10 +1245,8 @@ async function handleEditRemote(remote) {
     }
 
     // Get the current configuration for this remote
-    const configPath = localStorage.getItem('rcloneConfigPath') || null;
     const remoteConfig = await invoke('get_remote_config', {
-      remoteName: remote.name,
-      configPathOpt: configPath
+      remoteName: remote.name
     });
 
     // Now we have the plugin and remote config, so call a new function to open the edit form
@@ -1241,7 +1270,7 @@ async function openEditRemoteForm(remote, plugin, remoteConfig) {
 
   // Build plugin options for dropdown
   let pluginOptions = '';
-  const plugins = await invoke('get_available_plugins');
+  const plugins = (await invoke('get_available_plugins')).plugins;
   plugins.forEach(p => {
     const selected = p.name === plugin.name ? 'selected' : '';
     pluginOptions += `<option value="${p.name}" ${selected}>${p.display_name}</option>`;
@@ -1323,14 +1352,10 @@ async function openEditRemoteForm(remote, plugin, remoteConfig) {
     });
 
     try {
-      // Get config path
-      const configPath = localStorage.getItem('rcloneConfigPath') || null;
-
       // To edit, we first delete the existing remote and add the updated one
       // This is the simplest way to handle renaming since INI files don't support renaming sections
       const deleteResult = await invoke('delete_remote', {
-        remote_name: remote.name,
-        config_path_opt: configPath
+        remote_name: remote.name
       });
 
       if (!deleteResult.success) {
@@ -1341,8 +1366,7 @@ async function openEditRemoteForm(remote, plugin, remoteConfig) {
       // Then add the updated remote with new values
       const addResult = await invoke('add_remote_with_plugin', {
         plugin_name: newType,
-        config: config,
-        config_path_opt: configPath
+        config: config
       });
 
       if (addResult.success) {
@@ -1469,13 +1493,9 @@ async function handleDeleteRemote(remote) {
   }
 
   try {
-    // Get the config path from localStorage or use default (null)
-    const configPath = localStorage.getItem('rcloneConfigPath') || null;
-
     // Call the backend to delete the remote
     const result = await invoke('delete_remote', {
-      remoteName: remote.name,
-      config_path_opt: configPath
+      remoteName: remote.name
     });
 
     if (result.success) {
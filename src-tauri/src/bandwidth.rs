@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::filters::is_size;
+use crate::{rc, rcd, settings};
+use crate::rclone_config::target_remote;
+
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+// Bandwidth limits in rclone's --bwlimit syntax: a rate ("10M", "off", "10M:1M" for
+// upload:download) or a timetable ("08:00,512k 18:00,10M", "Mon-08:00,1M Sat-00:00,off")
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct BandwidthSettings {
+    pub global: Option<String>,
+    // Overrides the global limit for mounts and jobs using the remote
+    pub remotes: HashMap<String, String>,
+}
+
+// Result of applying a limit to one running mount or job
+#[derive(Serialize)]
+pub struct RuntimeLimit {
+    pub process: String,
+    pub limit: String,
+    pub error: Option<String>,
+}
+
+impl BandwidthSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        for limit in self.global.iter().chain(self.remotes.values()) {
+            validate_limit(limit)?;
+        }
+        Ok(())
+    }
+
+    // The limit for a process working on `targets` ("remote:path" or local paths). The first
+    // target's remote with its own limit wins, jobs list their destination first.
+    pub fn limit_for(&self, targets: &[&str]) -> Option<String> {
+        targets
+            .iter()
+            .filter_map(|target| target_remote(target))
+            .find_map(|remote| self.remotes.get(remote))
+            .or(self.global.as_ref())
+            .filter(|limit| !limit.trim().is_empty())
+            .cloned()
+    }
+
+    pub fn args(&self, targets: &[&str]) -> Vec<String> {
+        match self.limit_for(targets) {
+            Some(limit) => vec!["--bwlimit".to_string(), limit],
+            None => Vec::new(),
+        }
+    }
+}
+
+fn validate_limit(limit: &str) -> Result<(), String> {
+    let entries: Vec<&str> = limit.split_whitespace().collect();
+    let invalid = |part: &str| format!("'{}' in bandwidth limit '{}' is not valid", part, limit);
+
+    match entries.as_slice() {
+        [] => Err("Bandwidth limit cannot be empty".to_string()),
+        [rate] if !rate.contains(',') => validate_rate(rate).map_err(|_| invalid(rate)),
+        entries => {
+            for entry in entries {
+                let (when, rate) = entry.split_once(',').ok_or_else(|| invalid(entry))?;
+                validate_rate(rate).map_err(|_| invalid(entry))?;
+
+                let time = match when.split_once('-') {
+                    Some((day, time)) => {
+                        let day = day.to_lowercase();
+                        if !WEEKDAYS.iter().any(|d| day.starts_with(d)) {
+                            return Err(invalid(entry));
+                        }
+                        time
+                    }
+                    None => when,
+                };
+                let valid_time = time
+                    .split_once(':')
+                    .and_then(|(hours, minutes)| Some((hours.parse::<u32>().ok()?, minutes.parse::<u32>().ok()?)))
+                    .is_some_and(|(hours, minutes)| hours < 24 && minutes < 60);
+                if !valid_time {
+                    return Err(invalid(entry));
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+// "off", "10M" or "10M:1M"
+fn validate_rate(rate: &str) -> Result<(), ()> {
+    let valid = |part: &str| part.eq_ignore_ascii_case("off") || is_size(part);
+    match rate.split_once(':') {
+        Some((upload, download)) if valid(upload) && valid(download) => Ok(()),
+        None if valid(rate) => Ok(()),
+        _ => Err(()),
+    }
+}
+
+// Kept in the settings
+pub fn load() -> Result<BandwidthSettings, String> {
+    Ok(settings::load()?.bandwidth)
+}
+
+pub fn save(bandwidth: &BandwidthSettings) -> Result<(), String> {
+    bandwidth.validate()?;
+    settings::modify(|settings| {
+        settings.bandwidth = bandwidth.clone();
+        Ok(())
+    })
+}
+
+// Push the limits to mounts and jobs that are already running, through their rc servers
+pub async fn apply_runtime(settings: &BandwidthSettings) -> Vec<RuntimeLimit> {
+    let mut results = Vec::new();
+
+    // The daemon serves all its mounts and jobs with one limit, the global one. An attached
+    // daemon is someone else's to configure.
+    if let Some(endpoint) = rcd::local_active() {
+        let limit = settings.limit_for(&[]).unwrap_or_else(|| "off".to_string());
+        let error = endpoint.call("core/bwlimit", json!({ "rate": limit }), None).await.err();
+        results.push(RuntimeLimit { process: "rcd".to_string(), limit, error });
+    }
+
+    for (key, process) in rc::processes() {
+        let targets: Vec<&str> = process.targets.iter().map(String::as_str).collect();
+        let limit = settings.limit_for(&targets).unwrap_or_else(|| "off".to_string());
+        let error = process
+            .endpoint
+            .call("core/bwlimit", json!({ "rate": limit }), None)
+            .await
+            .err();
+        results.push(RuntimeLimit { process: key, limit, error });
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_rates_and_timetables() {
+        for limit in ["10M", "off", "512k", "10M:1M", "off:1M", "08:00,512k 18:00,10M", "Mon-08:00,1M Sat-00:00,off", "sun-23:59,1M:off"] {
+            assert!(validate_limit(limit).is_ok(), "{:?} rejected", limit);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_limits() {
+        for limit in ["", "  ", "fast", "10M:", "10M 1M", "24:00,1M", "08:60,1M", "8,1M", "Xyz-08:00,1M", "08:00,fast"] {
+            assert!(validate_limit(limit).is_err(), "{:?} accepted", limit);
+        }
+    }
+
+    #[test]
+    fn remote_limits_override_the_global_one() {
+        let settings = BandwidthSettings {
+            global: Some("10M".to_string()),
+            remotes: HashMap::from([("slow".to_string(), "1M".to_string())]),
+        };
+        assert_eq!(settings.limit_for(&["slow:dir", "/local"]).as_deref(), Some("1M"));
+        assert_eq!(settings.limit_for(&["/local", "fast:"]).as_deref(), Some("10M"));
+        assert!(BandwidthSettings::default().args(&["slow:"]).is_empty());
+    }
+}
@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::transfer;
+
+// A file both sides changed since the last run. bisync keeps both versions and renames
+// them to "<name>.conflict1"/"<name>.conflict2" (older rclone: "<name>..path1"/"<name>..path2").
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BisyncConflict {
+    // Renamed copy, as rclone logged it
+    pub path: String,
+    // The path both sides changed
+    pub original: String,
+    // "path1" (the job source), "path2" (the destination) or "unknown"
+    pub side: String,
+}
+
+// What a bisync run needs the UI to know beyond success or failure
+#[derive(Serialize, Default)]
+pub struct BisyncReport {
+    // No listings from a previous run, the job has to be run once with resync
+    pub needs_resync: bool,
+    pub resynced: bool,
+    // Lock file of a crashed run that was removed before retrying
+    pub recovered_lock: Option<PathBuf>,
+    pub conflicts: Vec<BisyncConflict>,
+}
+
+// Log messages, parsed from JSON log lines where possible
+fn messages(stderr: &str) -> impl Iterator<Item = String> + '_ {
+    stderr.lines().map(|line| match transfer::parse_log_line(line) {
+        Some(entry) => format!("{} {}", entry.object, entry.msg),
+        None => line.to_string(),
+    })
+}
+
+// First runs, and runs after a critical error, fail until bisync is run with --resync
+pub fn needs_resync(stderr: &str) -> bool {
+    messages(stderr).any(|msg| {
+        let msg = msg.to_lowercase();
+        msg.contains("cannot find prior path1 or path2 listings") || msg.contains("must run --resync")
+    })
+}
+
+// The lock file a run refused to start because of, "prior lock file found: <path>"
+pub fn blocking_lock(stderr: &str) -> Option<PathBuf> {
+    messages(stderr).find_map(|msg| {
+        let (_, rest) = msg.split_once("prior lock file found: ")?;
+        rest.split_whitespace().next().map(PathBuf::from)
+    })
+}
+
+// Remove a lock file left behind by a bisync that no longer runs.
+// The lock holds the pid of its owner, as plain text in older rclone and as JSON since 1.66.
+// A lock whose owner can't be told is left alone, deleting it could let two runs overlap.
+pub fn remove_stale_lock(lock_path: &Path) -> Result<(), String> {
+    #[derive(Deserialize)]
+    struct LockFile {
+        #[serde(rename = "PID")]
+        pid: serde_json::Value,
+        // Set by --max-lock, an expired lock is stale even if its pid was reused
+        #[serde(rename = "TimeExpires", default)]
+        time_expires: Option<String>,
+    }
+
+    let content = fs::read_to_string(lock_path)
+        .map_err(|e| format!("Failed to read {}: {}", lock_path.display(), e))?;
+    let (pid, expired) = match serde_json::from_str::<LockFile>(&content) {
+        Ok(lock) => {
+            let pid = match lock.pid {
+                serde_json::Value::String(pid) => pid.parse::<u32>().ok(),
+                serde_json::Value::Number(pid) => pid.as_u64().and_then(|pid| u32::try_from(pid).ok()),
+                _ => None,
+            };
+            // Go writes a zero time when there is no expiry
+            let expired = lock
+                .time_expires
+                .and_then(|time| chrono::DateTime::parse_from_rfc3339(&time).ok())
+                .is_some_and(|time| time.timestamp() > 0 && time < chrono::Utc::now());
+            (pid, expired)
+        }
+        Err(_) => (content.trim().parse::<u32>().ok(), false),
+    };
+
+    if !expired {
+        let pid = pid.ok_or_else(|| format!("Can't tell which process holds {}, remove it by hand if no bisync is running", lock_path.display()))?;
+        if Path::new(&format!("/proc/{}", pid)).exists() {
+            return Err(format!("Another bisync is still running (pid {})", pid));
+        }
+    }
+
+    fs::remove_file(lock_path).map_err(|e| format!("Failed to remove {}: {}", lock_path.display(), e))
+}
+
+// Conflicts bisync reported, in the order they were logged
+pub fn conflicts(stderr: &str, path1: &str, path2: &str) -> Vec<BisyncConflict> {
+    let mut conflicts: Vec<BisyncConflict> = Vec::new();
+
+    for msg in messages(stderr) {
+        for token in msg.split_whitespace() {
+            // Plain log lines are "<path>: <message>"
+            let token = token.trim_end_matches([':', ',']);
+            let Some(original) = conflict_original(token) else {
+                continue;
+            };
+            if conflicts.iter().any(|c| c.path == token) {
+                continue;
+            }
+
+            let side = if token.starts_with(path1) || token.ends_with("..path1") {
+                "path1"
+            } else if token.starts_with(path2) || token.ends_with("..path2") {
+                "path2"
+            } else {
+                "unknown"
+            };
+            conflicts.push(BisyncConflict {
+                path: token.to_string(),
+                original,
+                side: side.to_string(),
+            });
+        }
+    }
+
+    conflicts
+}
+
+// "dir/file.txt.conflict1" -> "dir/file.txt", "dir/file.txt..path1" -> "dir/file.txt"
+fn conflict_original(token: &str) -> Option<String> {
+    if let Some(original) = token.strip_suffix("..path1").or_else(|| token.strip_suffix("..path2")) {
+        return Some(original.to_string());
+    }
+
+    let index = token.rfind(".conflict")?;
+    let suffix = &token[index + ".conflict".len()..];
+    // Newer rclone puts the number before the extension, "file.conflict1.txt"
+    let digits = suffix.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 {
+        return None;
+    }
+    Some(format!("{}{}", &token[..index], &suffix[digits..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A pid above the kernel's pid_max, never running
+    const DEAD_PID: u32 = 4_999_999;
+
+    fn lock_file(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("de_rclone-bisync-{}-{}.lck", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn keeps_a_json_lock_held_by_a_running_process() {
+        let path = lock_file("live", &format!(r#"{{"Session":"a..b","PID":"{}","TimeRenewed":"2024-01-01T00:00:00Z","TimeExpires":"9999-12-31T00:00:00Z"}}"#, std::process::id()));
+        let err = remove_stale_lock(&path).unwrap_err();
+        assert!(err.contains("still running"), "{}", err);
+        assert!(path.exists());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn removes_an_expired_json_lock() {
+        let path = lock_file("expired", &format!(r#"{{"PID":"{}","TimeExpires":"2024-01-01T00:00:00.123456789+01:00"}}"#, std::process::id()));
+        remove_stale_lock(&path).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn reads_plain_text_locks() {
+        let path = lock_file("plain-live", &format!("{}\n", std::process::id()));
+        assert!(remove_stale_lock(&path).is_err());
+        fs::remove_file(&path).unwrap();
+
+        let path = lock_file("plain-dead", &format!("{}\n", DEAD_PID));
+        remove_stale_lock(&path).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn keeps_a_lock_without_a_readable_pid() {
+        for (name, content) in [("garbage", "locked by someone"), ("no-pid", r#"{"Session":"a..b"}"#), ("zero-expiry", r#"{"PID":"x","TimeExpires":"0001-01-01T00:00:00Z"}"#)] {
+            let path = lock_file(name, content);
+            let err = remove_stale_lock(&path).unwrap_err();
+            assert!(err.contains("Can't tell"), "{}: {}", name, err);
+            assert!(path.exists());
+            fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn finds_the_original_of_renamed_conflicts() {
+        assert_eq!(conflict_original("dir/file.txt.conflict1").as_deref(), Some("dir/file.txt"));
+        assert_eq!(conflict_original("dir/file.conflict2.txt").as_deref(), Some("dir/file.txt"));
+        assert_eq!(conflict_original("dir/file.txt..path1").as_deref(), Some("dir/file.txt"));
+        assert_eq!(conflict_original("dir/file.txt"), None);
+        assert_eq!(conflict_original("notes.conflicts.md"), None);
+    }
+
+    #[test]
+    fn reads_conflicts_with_their_side() {
+        let stderr = "NOTICE: drive:docs/a.txt.conflict1: renamed\nNOTICE: /home/me/docs/a.txt.conflict2: renamed\nNOTICE: drive:docs/a.txt.conflict1: again\n";
+        let found = conflicts(stderr, "drive:docs", "/home/me/docs");
+        let sides: Vec<(&str, &str)> = found.iter().map(|c| (c.original.as_str(), c.side.as_str())).collect();
+        assert_eq!(sides, vec![("drive:docs/a.txt", "path1"), ("/home/me/docs/a.txt", "path2")]);
+    }
+
+    #[test]
+    fn finds_the_blocking_lock_file() {
+        let stderr = r#"{"level":"error","msg":"Bisync critical error: prior lock file found: /home/me/.cache/rclone/bisync/a..b.lck"}"#;
+        assert_eq!(blocking_lock(stderr), Some(PathBuf::from("/home/me/.cache/rclone/bisync/a..b.lck")));
+        assert_eq!(blocking_lock("ERROR: something else"), None);
+    }
+}
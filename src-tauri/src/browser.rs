@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+use crate::{rcd, runner};
+
+pub const DEFAULT_PAGE_SIZE: usize = 500;
+
+// Listings are kept this long so paging through a big directory lists it only once
+const LISTING_TTL: Duration = Duration::from_secs(60);
+
+// One file or directory as returned by `rclone lsjson`
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct RemoteEntry {
+    pub path: String,
+    pub name: String,
+    // -1 when the backend doesn't know, e.g. for directories or Google Docs
+    #[serde(default)]
+    pub size: i64,
+    #[serde(default)]
+    pub mod_time: String,
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default)]
+    pub is_dir: bool,
+    #[serde(default)]
+    pub hashes: HashMap<String, String>,
+}
+
+// One page of a directory listing
+#[derive(Serialize)]
+pub struct DirListing {
+    pub remote: String,
+    pub path: String,
+    pub entries: Vec<RemoteEntry>,
+    // Number of entries in the whole directory
+    pub total: usize,
+    pub offset: usize,
+    pub has_more: bool,
+}
+
+// config path, "remote:path", with hashes
+type ListingKey = (PathBuf, String, bool);
+type Listing = (Instant, Vec<RemoteEntry>);
+static LISTINGS: LazyLock<Mutex<HashMap<ListingKey, Listing>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// "remote:path" for a path inside a remote, tolerating leading/trailing slashes from the UI
+pub fn remote_target(remote_name: &str, path: &str) -> String {
+    format!("{}:{}", remote_name, path.trim_matches('/'))
+}
+
+// List a directory of a remote without mounting it.
+// The full listing is fetched once and then served in pages of `limit` entries, directories first.
+#[allow(clippy::too_many_arguments)]
+pub async fn list_dir(
+    remote_name: &str,
+    path: &str,
+    config_path: &Path,
+    offset: usize,
+    limit: usize,
+    with_hashes: bool,
+    refresh: bool,
+    operation_id: Option<String>,
+) -> Result<DirListing, String> {
+    let target = remote_target(remote_name, path);
+    let key = (config_path.to_path_buf(), target.clone(), with_hashes);
+
+    let cached = if refresh {
+        None
+    } else {
+        let listings = LISTINGS.lock().unwrap_or_else(|e| e.into_inner());
+        listings
+            .get(&key)
+            .filter(|(listed, _)| listed.elapsed() < LISTING_TTL)
+            .map(|(_, entries)| entries.clone())
+    };
+
+    let entries = match cached {
+        Some(entries) => entries,
+        None => {
+            let entries = lsjson(&target, config_path, with_hashes, operation_id).await?;
+            let mut listings = LISTINGS.lock().unwrap_or_else(|e| e.into_inner());
+            // Old listings are only useful for paging, drop them as we go
+            listings.retain(|_, (listed, _)| listed.elapsed() < LISTING_TTL);
+            listings.insert(key, (Instant::now(), entries.clone()));
+            entries
+        }
+    };
+
+    let total = entries.len();
+    let limit = if limit == 0 { DEFAULT_PAGE_SIZE } else { limit };
+    let page: Vec<RemoteEntry> = entries.into_iter().skip(offset).take(limit).collect();
+
+    Ok(DirListing {
+        remote: remote_name.to_string(),
+        path: path.trim_matches('/').to_string(),
+        has_more: offset + page.len() < total,
+        entries: page,
+        total,
+        offset,
+    })
+}
+
+// Forget cached listings of "remote:path", e.g. after files in it changed
+pub fn invalidate(target: &str) {
+    let target = target.trim_end_matches('/');
+    let mut listings = LISTINGS.lock().unwrap_or_else(|e| e.into_inner());
+    listings.retain(|(_, listed, _), _| listed.trim_end_matches('/') != target);
+}
+
+async fn lsjson(target: &str, config_path: &Path, with_hashes: bool, operation_id: Option<String>) -> Result<Vec<RemoteEntry>, String> {
+    let rcd = rcd::active_for(config_path).zip(rcd::split_fs(target));
+    let mut entries: Vec<RemoteEntry> = match rcd {
+        // operations/list returns the same entries as lsjson
+        Some((endpoint, (fs, remote))) => {
+            let params = serde_json::json!({ "fs": fs, "remote": remote, "opt": { "showHash": with_hashes } });
+            let reply = endpoint
+                .call("operations/list", params, operation_id)
+                .await
+                .map_err(|e| format!("Failed to list {}: {}", target, e))?;
+            serde_json::from_value(reply.get("list").cloned().unwrap_or_default())
+                .map_err(|e| format!("Failed to parse rc operations/list reply: {}", e))?
+        }
+        None => {
+            let mut cmd = Command::new("rclone");
+            cmd.arg("lsjson").arg(target).arg("--config").arg(config_path);
+            if with_hashes {
+                cmd.arg("--hash");
+            }
+
+            let output = runner::output("list", operation_id, cmd).await?;
+
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(format!("Failed to list {}: {}", target, stderr));
+            }
+
+            serde_json::from_slice(&output.stdout)
+                .map_err(|e| format!("Failed to parse rclone lsjson output: {}", e))?
+        }
+    };
+
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+
+    Ok(entries)
+}
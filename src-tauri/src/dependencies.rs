@@ -0,0 +1,109 @@
+use serde::Serialize;
+
+use crate::rclone_config::{target_remote, ConfigSection};
+
+// A remote and how it relates to the others
+#[derive(Serialize)]
+pub struct RemoteNode {
+    pub name: String,
+    pub r#type: String,
+    // Remotes this one wraps
+    pub depends_on: Vec<String>,
+    // Remotes that wrap this one
+    pub dependents: Vec<String>,
+}
+
+// `from` wraps `to` through the "remote:path" `target`
+#[derive(Serialize)]
+pub struct RemoteEdge {
+    pub from: String,
+    pub to: String,
+    pub target: String,
+    // `to` is not defined in the config, so `from` is already broken
+    pub missing: bool,
+}
+
+#[derive(Serialize)]
+pub struct RemoteGraph {
+    pub nodes: Vec<RemoteNode>,
+    pub edges: Vec<RemoteEdge>,
+}
+
+pub fn build_graph(sections: &[ConfigSection]) -> RemoteGraph {
+    let remotes: Vec<&ConfigSection> = sections.iter().filter(|s| !s.remote_type().is_empty()).collect();
+    let mut edges = Vec::new();
+
+    for section in &remotes {
+        for target in section.upstream_targets() {
+            if let Some(name) = target_remote(&target) {
+                edges.push(RemoteEdge {
+                    from: section.name.clone(),
+                    to: name.to_string(),
+                    missing: !remotes.iter().any(|r| r.name == name),
+                    target,
+                });
+            }
+        }
+    }
+
+    let nodes = remotes
+        .iter()
+        .map(|section| RemoteNode {
+            name: section.name.clone(),
+            r#type: section.remote_type().to_string(),
+            depends_on: unique(edges.iter().filter(|e| e.from == section.name).map(|e| &e.to)),
+            dependents: unique(edges.iter().filter(|e| e.to == section.name).map(|e| &e.from)),
+        })
+        .collect();
+
+    RemoteGraph { nodes, edges }
+}
+
+// `name` and everything that wraps it directly or through other wrappers, e.g. crypt over alias over base
+pub fn dependents_closure(sections: &[ConfigSection], name: &str) -> Vec<String> {
+    let mut closure = vec![name.to_string()];
+    let mut i = 0;
+
+    while i < closure.len() {
+        for dependent in crate::rclone_config::dependents_of(sections, &closure[i]) {
+            if !closure.contains(&dependent) {
+                closure.push(dependent);
+            }
+        }
+        i += 1;
+    }
+
+    closure
+}
+
+fn unique<'a>(names: impl Iterator<Item = &'a String>) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for name in names {
+        if !result.contains(name) {
+            result.push(name.clone());
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rclone_config::parse_sections;
+
+    #[test]
+    fn collects_dependents_transitively() {
+        let sections = parse_sections(
+            "[base]\ntype = drive\n[alias]\ntype = alias\nremote = base:dir\n[secret]\ntype = crypt\nremote = alias:\n[pool]\ntype = union\nupstreams = secret: other:\n[other]\ntype = local\n",
+        );
+        assert_eq!(dependents_closure(&sections, "base"), vec!["base", "alias", "secret", "pool"]);
+        assert_eq!(dependents_closure(&sections, "other"), vec!["other", "pool"]);
+        assert_eq!(dependents_closure(&sections, "pool"), vec!["pool"]);
+    }
+
+    #[test]
+    fn stops_at_cycles() {
+        let sections = parse_sections("[a]\ntype = alias\nremote = b:\n[b]\ntype = alias\nremote = a:\n");
+        assert_eq!(dependents_closure(&sections, "a"), vec!["a", "b"]);
+    }
+}
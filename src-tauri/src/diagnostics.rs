@@ -0,0 +1,284 @@
+use serde::Serialize;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::process::Command;
+
+use crate::rclone_config;
+use crate::runner::{self, Operation, RunError};
+
+const PROBE_CONTENT: &str = "de_rclone connection probe\n";
+
+// One check of a diagnostics run
+#[derive(Serialize)]
+pub struct DiagnosticStep {
+    pub name: String,
+    pub command: String,
+    pub success: bool,
+    // The backend doesn't support this check, which is not a failure
+    pub skipped: bool,
+    pub duration_ms: u128,
+    pub message: String,
+    // dns, auth, tls, permission, quota, network, timeout, not_found, cancelled or unknown
+    pub failure: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DiagnosticsReport {
+    pub remote: String,
+    pub success: bool,
+    pub duration_ms: u128,
+    pub steps: Vec<DiagnosticStep>,
+    // Category of the first failed step
+    pub failure: Option<String>,
+}
+
+// Shared by all steps of one run
+struct Probe<'a> {
+    operation: &'a Operation,
+    config_path: Option<String>,
+    step_timeout: Option<Duration>,
+}
+
+// Run a bounded set of checks against a remote:
+//   about - quota query, proves auth works without listing anything
+//   list  - top level listing with a timeout
+//   write/read/delete - a small probe file in a temporary directory, only when `write_probe` is set
+// Each step gets `timeout_secs`, or the configured diagnose timeout.
+pub async fn diagnose(operation: &Operation, remote_name: &str, config_path_opt: Option<String>, write_probe: bool, timeout_secs: Option<u64>) -> Result<DiagnosticsReport, String> {
+    let config_path = Some(rclone_config::resolve_config_path(config_path_opt)?.to_string_lossy().to_string());
+    let probe = Probe {
+        operation,
+        config_path,
+        step_timeout: match timeout_secs {
+            Some(secs) => Some(Duration::from_secs(secs)).filter(|t| !t.is_zero()),
+            None => runner::timeouts().get("diagnose"),
+        },
+    };
+    let remote = format!("{}:", remote_name);
+    let started = Instant::now();
+    let mut steps = Vec::new();
+
+    let about = probe.step("about", &["about", &remote, "--json"]).await;
+    // Many backends have no quota API, that says nothing about the connection
+    let about = if !about.success && about.message.contains("doesn't support about") {
+        DiagnosticStep { skipped: true, success: true, failure: None, ..about }
+    } else {
+        about
+    };
+    let unreachable = about.failure.is_some();
+    steps.push(about);
+
+    // Further steps would fail the same way when the remote can't even be reached
+    if !unreachable {
+        steps.push(probe.step("list", &["lsjson", &remote, "--max-depth", "1"]).await);
+    }
+
+    if write_probe && steps.iter().all(|s| s.success) {
+        steps.extend(probe.write_read_delete(&remote).await?);
+    }
+
+    let failure = steps.iter().find_map(|s| s.failure.clone());
+
+    Ok(DiagnosticsReport {
+        remote: remote_name.to_string(),
+        success: failure.is_none(),
+        duration_ms: started.elapsed().as_millis(),
+        steps,
+        failure,
+    })
+}
+
+impl Probe<'_> {
+    async fn write_read_delete(&self, remote: &str) -> Result<Vec<DiagnosticStep>, String> {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let probe_dir = format!("{}.de_rclone-probe-{}", remote, unique);
+        let probe_file = format!("{}/probe.txt", probe_dir);
+
+        let local_file = std::env::temp_dir().join(format!("de_rclone-probe-{}.txt", unique));
+        std::fs::write(&local_file, PROBE_CONTENT)
+            .map_err(|e| format!("Failed to create probe file: {}", e))?;
+        let local_path = local_file.to_string_lossy().to_string();
+
+        let mut steps = Vec::new();
+
+        let write = self.step("write", &["copyto", &local_path, &probe_file]).await;
+        let written = write.success;
+        steps.push(write);
+        let _ = std::fs::remove_file(&local_file);
+
+        if written {
+            let started = Instant::now();
+            let output = self.run(&["cat", &probe_file]).await;
+            let mut read = step_from_output("read", &["cat", &probe_file], started, output);
+            if read.success && read.message != PROBE_CONTENT.trim() {
+                read.success = false;
+                read.failure = Some("unknown".to_string());
+                read.message = "Probe file content changed between write and read".to_string();
+            }
+            steps.push(read);
+        }
+
+        // Clean up even after a failed write, it may have created the directory
+        steps.push(self.step("delete", &["purge", &probe_dir]).await);
+
+        Ok(steps)
+    }
+
+    async fn step(&self, name: &str, args: &[&str]) -> DiagnosticStep {
+        let started = Instant::now();
+        let output = self.run(args).await;
+        step_from_output(name, args, started, output)
+    }
+
+    async fn run(&self, args: &[&str]) -> Result<std::process::Output, RunError> {
+        let mut cmd = Command::new("rclone");
+        cmd.args(args);
+        // Fail fast instead of retrying for minutes, we only want to know if it works
+        cmd.args(["--retries", "1", "--low-level-retries", "1"]);
+        if let Some(config_path) = &self.config_path {
+            cmd.arg("--config").arg(config_path);
+        }
+
+        self.operation.output_with_timeout(cmd, self.step_timeout).await
+    }
+}
+
+fn step_from_output(name: &str, args: &[&str], started: Instant, output: Result<std::process::Output, RunError>) -> DiagnosticStep {
+    let duration_ms = started.elapsed().as_millis();
+    let command = format!("rclone {}", args.join(" "));
+
+    let (success, message, failure) = match output {
+        Ok(output) if output.status.success() => (true, String::from_utf8_lossy(&output.stdout).trim().to_string(), None),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            let failure = classify_failure(&stderr);
+            (false, stderr, Some(failure.to_string()))
+        }
+        Err(e @ RunError::TimedOut(..)) => (false, e.to_string(), Some("timeout".to_string())),
+        Err(e @ RunError::Cancelled(_)) => (false, e.to_string(), Some("cancelled".to_string())),
+        Err(e) => (false, e.to_string(), Some("unknown".to_string())),
+    };
+
+    DiagnosticStep {
+        name: name.to_string(),
+        command,
+        success,
+        skipped: false,
+        duration_ms,
+        message,
+        failure,
+    }
+}
+
+// Ways backends report an HTTP status, e.g. "HTTP error 403", "googleapi: Error 404:",
+// "StatusCode: 401". Bare numbers could just as well be sizes, file names or object IDs.
+const HTTP_STATUS_PREFIXES: [&str; 7] = [
+    "http error ",
+    "http status ",
+    "status code ",
+    "status code: ",
+    "statuscode: ",
+    "statuscode=",
+    "googleapi: error ",
+];
+
+// HTTP status codes mentioned in lowercased rclone output
+fn http_statuses(text: &str) -> Vec<u16> {
+    HTTP_STATUS_PREFIXES
+        .iter()
+        .flat_map(|prefix| text.match_indices(prefix).map(move |(i, _)| &text[i + prefix.len()..]))
+        .filter_map(|rest| {
+            let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+            if digits.len() == 3 { digits.parse().ok() } else { None }
+        })
+        .collect()
+}
+
+// `word` not being part of a longer word, so "eof" doesn't match "geofence"
+fn contains_word(text: &str, word: &str) -> bool {
+    text.match_indices(word).any(|(i, _)| {
+        let before = text[..i].chars().next_back();
+        let after = text[i + word.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+// Guess why rclone failed from its error output
+pub fn classify_failure(stderr: &str) -> &'static str {
+    let text = stderr.to_lowercase();
+    let any = |patterns: &[&str]| patterns.iter().any(|p| text.contains(p));
+    let statuses = http_statuses(&text);
+    let status = |code: u16| statuses.contains(&code);
+
+    if any(&["no such host", "server misbehaving", "name resolution", "name or service not known", "dial tcp: lookup"]) {
+        "dns"
+    } else if any(&["x509", "tls:", "certificate"]) {
+        "tls"
+    } else if status(507) || any(&["quota", "storage full", "insufficient storage", "insufficient_space"]) {
+        "quota"
+    } else if status(401)
+        || any(&[
+            "unauthorized",
+            "unauthenticated",
+            "invalid_grant",
+            "invalid_client",
+            "token expired",
+            "authentication failed",
+            "unable to authenticate",
+            "invalidaccesskeyid",
+            "signaturedoesnotmatch",
+            "login failed",
+            "wrong password",
+        ])
+    {
+        "auth"
+    } else if status(403) || any(&["forbidden", "accessdenied", "access denied", "permission denied", "insufficientpermissions"]) {
+        "permission"
+    } else if any(&["timeout", "timed out", "deadline exceeded"]) {
+        "timeout"
+    } else if contains_word(&text, "eof")
+        || any(&["connection refused", "connection reset", "no route to host", "network is unreachable"])
+    {
+        "network"
+    } else if status(404) || any(&["not found", "nosuchbucket", "didn't find section"]) {
+        "not_found"
+    } else {
+        "unknown"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_http_statuses_in_context() {
+        assert_eq!(classify_failure("googleapi: Error 403: The user does not have sufficient permissions"), "permission");
+        assert_eq!(classify_failure("HTTP error 401 (401 Unauthorized) returned body"), "auth");
+        assert_eq!(classify_failure("couldn't list directory: StatusCode: 404"), "not_found");
+        assert_eq!(classify_failure("upload failed: HTTP error 507"), "quota");
+    }
+
+    #[test]
+    fn ignores_bare_numbers() {
+        assert_eq!(classify_failure("failed to copy report-401.pdf: 4034 bytes written"), "unknown");
+        assert_eq!(classify_failure("object 5074041 changed during upload"), "unknown");
+    }
+
+    #[test]
+    fn matches_eof_as_a_word_only() {
+        assert_eq!(classify_failure("read tcp 10.0.0.2:443: unexpected EOF"), "network");
+        assert_eq!(classify_failure("Failed to read geofence/area.json"), "unknown");
+    }
+
+    #[test]
+    fn classifies_common_errors() {
+        assert_eq!(classify_failure("dial tcp: lookup example.invalid: no such host"), "dns");
+        assert_eq!(classify_failure("x509: certificate signed by unknown authority"), "tls");
+        assert_eq!(classify_failure("oauth2: cannot fetch token: invalid_grant"), "auth");
+        assert_eq!(classify_failure("context deadline exceeded"), "timeout");
+        assert_eq!(classify_failure("didn't find section in config file"), "not_found");
+    }
+}
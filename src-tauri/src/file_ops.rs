@@ -0,0 +1,288 @@
+use serde_json::json;
+use std::path::Path;
+use std::time::Instant;
+use tokio::process::Command;
+
+use crate::rclone_config::target_remote;
+use crate::rc::RcEndpoint;
+use crate::{browser, rcd, runner, usage, CommandResult};
+
+// A file operation on remotes or local paths, each maps onto one rclone command
+pub enum FileOp {
+    Copy { source: String, destination: String },
+    Move { source: String, destination: String },
+    Delete { path: String },
+    Mkdir { path: String },
+    Rmdir { path: String },
+    Purge { path: String },
+}
+
+impl FileOp {
+    fn name(&self) -> &'static str {
+        match self {
+            FileOp::Copy { .. } => "copy",
+            FileOp::Move { .. } => "move",
+            FileOp::Delete { .. } => "delete",
+            FileOp::Mkdir { .. } => "mkdir",
+            FileOp::Rmdir { .. } => "rmdir",
+            FileOp::Purge { .. } => "purge",
+        }
+    }
+
+    fn args(&self) -> Vec<&str> {
+        match self {
+            FileOp::Copy { source, destination } => vec!["copyto", source, destination],
+            FileOp::Move { source, destination } => vec!["moveto", source, destination],
+            FileOp::Delete { path } => vec!["deletefile", path],
+            FileOp::Mkdir { path } => vec!["mkdir", path],
+            FileOp::Rmdir { path } => vec!["rmdir", path],
+            FileOp::Purge { path } => vec!["purge", path],
+        }
+    }
+
+    // The equivalent rc method and parameters, None when a path can't be addressed through rc.
+    // operations/copyfile and movefile only take single files, directories go through sync/*
+    // like copyto and moveto do.
+    fn rc_call(&self, source_is_dir: bool) -> Option<(&'static str, serde_json::Value)> {
+        let path_params = |path: &str| {
+            let (fs, remote) = rcd::split_fs(path)?;
+            Some(json!({ "fs": fs, "remote": remote }))
+        };
+        let transfer_params = |source: &str, destination: &str| {
+            let (src_fs, src_remote) = rcd::split_fs(source)?;
+            let (dst_fs, dst_remote) = rcd::split_fs(destination)?;
+            Some(json!({ "srcFs": src_fs, "srcRemote": src_remote, "dstFs": dst_fs, "dstRemote": dst_remote }))
+        };
+        let dir_params = |source: &str, destination: &str| {
+            rcd::split_fs(source)?;
+            rcd::split_fs(destination)?;
+            Some(json!({ "srcFs": source.trim(), "dstFs": destination.trim() }))
+        };
+
+        match self {
+            FileOp::Copy { source, destination } if source_is_dir => Some(("sync/copy", dir_params(source, destination)?)),
+            FileOp::Move { source, destination } if source_is_dir => Some(("sync/move", dir_params(source, destination)?)),
+            FileOp::Copy { source, destination } => Some(("operations/copyfile", transfer_params(source, destination)?)),
+            FileOp::Move { source, destination } => Some(("operations/movefile", transfer_params(source, destination)?)),
+            FileOp::Delete { path } => Some(("operations/deletefile", path_params(path)?)),
+            FileOp::Mkdir { path } => Some(("operations/mkdir", path_params(path)?)),
+            FileOp::Rmdir { path } => Some(("operations/rmdir", path_params(path)?)),
+            FileOp::Purge { path } => Some(("operations/purge", path_params(path)?)),
+        }
+    }
+
+    // Paths whose contents change, for cache invalidation
+    fn touched(&self) -> Vec<&str> {
+        match self {
+            FileOp::Copy { destination, .. } => vec![destination],
+            FileOp::Move { source, destination } => vec![source, destination],
+            FileOp::Delete { path } | FileOp::Mkdir { path } | FileOp::Rmdir { path } | FileOp::Purge { path } => vec![path],
+        }
+    }
+
+    // Copies and moves run as long as the transfer takes, the rest should be quick
+    fn timeout_kind(&self) -> &'static str {
+        match self {
+            FileOp::Copy { .. } | FileOp::Move { .. } => "transfer",
+            _ => "file_op",
+        }
+    }
+}
+
+// Run a file operation. Destructive ones - deleting, purging a directory tree or overwriting
+// an existing destination - only run with `confirmed`, otherwise the result asks for confirmation.
+pub async fn run(op: FileOp, confirmed: bool, config_path: &Path, operation_id: Option<String>) -> Result<CommandResult, String> {
+    let operation = runner::Operation::start(op.timeout_kind(), operation_id)?;
+
+    if !confirmed {
+        let reason = match &op {
+            FileOp::Delete { path } => Some(format!("Delete {}?", path)),
+            FileOp::Purge { path } => Some(format!("Delete {} and everything in it?", path)),
+            FileOp::Copy { destination, .. } | FileOp::Move { destination, .. } => {
+                if exists(&operation, destination, config_path).await? {
+                    Some(format!("{} already exists, overwrite it?", destination))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        if let Some(reason) = reason {
+            return Ok(CommandResult {
+                success: false,
+                message: reason,
+                details: Some(json!({
+                    "operation": op.name(),
+                    "requires_confirmation": true,
+                    "paths": op.touched(),
+                })),
+            });
+        }
+    }
+
+    let rc_call = match rcd::active_for(config_path) {
+        Some(endpoint) => {
+            let source_is_dir = match &op {
+                FileOp::Copy { source, .. } | FileOp::Move { source, .. } => is_dir_rc(&endpoint, source).await?,
+                _ => false,
+            };
+            op.rc_call(source_is_dir).map(|call| (endpoint, call))
+        }
+        None => None,
+    };
+    let args = op.args();
+    let command = match &rc_call {
+        Some((_, (method, _))) => format!("rc {}", method),
+        None => format!("rclone {}", args.join(" ")),
+    };
+
+    let started = Instant::now();
+    let result = match rc_call {
+        Some((endpoint, (method, params))) => run_rc(&endpoint, method, params, &operation).await,
+        None => {
+            let mut cmd = Command::new("rclone");
+            cmd.args(&args).arg("--config").arg(config_path);
+            let output = operation.output(cmd).await?;
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+            }
+        }
+    };
+    let duration_ms = started.elapsed().as_millis();
+
+    for path in op.touched() {
+        invalidate_caches(path);
+    }
+
+    let details = json!({
+        "operation": op.name(),
+        "command": command,
+        "paths": op.touched(),
+        "duration_ms": duration_ms,
+    });
+
+    match result {
+        Ok(()) => Ok(CommandResult {
+            success: true,
+            message: success_message(&op),
+            details: Some(details),
+        }),
+        Err(e) => Err(format!("{} failed: {}", op.name(), e)),
+    }
+}
+
+// Run the operation as an async rc job so it can be cancelled like the CLI process
+async fn run_rc(endpoint: &RcEndpoint, method: &str, mut params: serde_json::Value, operation: &runner::Operation) -> Result<(), String> {
+    params["_async"] = json!(true);
+    let reply = endpoint.call(method, params, None).await?;
+    let job_id = reply
+        .get("jobid")
+        .and_then(serde_json::Value::as_i64)
+        .ok_or("rclone rcd did not return a job id")?;
+
+    let status = rcd::wait_for_job(endpoint, job_id, operation, |_| {}).await?;
+    match status.get("success").and_then(serde_json::Value::as_bool) {
+        Some(true) => Ok(()),
+        _ => Err(status.get("error").and_then(serde_json::Value::as_str).unwrap_or("unknown error").to_string()),
+    }
+}
+
+// Whether a path is a directory according to the daemon, false for paths it can't address
+async fn is_dir_rc(endpoint: &RcEndpoint, path: &str) -> Result<bool, String> {
+    let Some((fs, remote)) = rcd::split_fs(path) else {
+        return Ok(false);
+    };
+    let reply = endpoint.call("operations/stat", json!({ "fs": fs, "remote": remote }), None).await?;
+    Ok(reply.pointer("/item/IsDir").and_then(serde_json::Value::as_bool).unwrap_or(false))
+}
+
+fn success_message(op: &FileOp) -> String {
+    match op {
+        FileOp::Copy { source, destination } => format!("Copied {} to {}", source, destination),
+        FileOp::Move { source, destination } => format!("Moved {} to {}", source, destination),
+        FileOp::Delete { path } => format!("Deleted {}", path),
+        FileOp::Mkdir { path } => format!("Created directory {}", path),
+        FileOp::Rmdir { path } => format!("Removed directory {}", path),
+        FileOp::Purge { path } => format!("Deleted {} and its contents", path),
+    }
+}
+
+// Whether a file or directory exists, via `rclone lsjson --stat`
+async fn exists(operation: &runner::Operation, path: &str, config_path: &Path) -> Result<bool, String> {
+    let mut cmd = Command::new("rclone");
+    cmd.args(["lsjson", "--stat", path]).arg("--config").arg(config_path);
+
+    let output = operation
+        .output_with_timeout(cmd, runner::timeouts().get("file_op"))
+        .await?;
+    Ok(output.status.success())
+}
+
+// Listings of the parent directory and the remote's usage are stale after a change
+pub fn invalidate_caches(path: &str) {
+    let parent = match path.trim_end_matches('/').rsplit_once('/') {
+        Some((parent, _)) => parent.to_string(),
+        None => path.split_once(':').map(|(remote, _)| format!("{}:", remote)).unwrap_or_default(),
+    };
+    browser::invalidate(&parent);
+    browser::invalidate(path);
+
+    if let Some(remote) = target_remote(path) {
+        usage::invalidate(remote);
+    }
+}
+
+// "remote:dir/new_name" for renaming "remote:dir/old_name"
+pub fn renamed_path(path: &str, new_name: &str) -> Result<String, String> {
+    if new_name.is_empty() || new_name.contains('/') || new_name == "." || new_name == ".." {
+        return Err(format!("'{}' is not a valid name", new_name));
+    }
+
+    let path = path.trim_end_matches('/');
+    Ok(match path.rsplit_once('/') {
+        Some((parent, _)) => format!("{}/{}", parent, new_name),
+        // Top level of a remote, "remote:name"
+        None => match path.split_once(':') {
+            Some((remote, _)) => format!("{}:{}", remote, new_name),
+            None => new_name.to_string(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renames_within_the_parent_directory() {
+        assert_eq!(renamed_path("drive:docs/a.txt", "b.txt").unwrap(), "drive:docs/b.txt");
+        assert_eq!(renamed_path("drive:docs/old/", "new").unwrap(), "drive:docs/new");
+        assert_eq!(renamed_path("drive:a.txt", "b.txt").unwrap(), "drive:b.txt");
+        assert_eq!(renamed_path("/home/me/a.txt", "b.txt").unwrap(), "/home/me/b.txt");
+    }
+
+    #[test]
+    fn copies_directories_through_sync() {
+        let op = FileOp::Copy { source: "drive:photos".to_string(), destination: "/backup/photos".to_string() };
+        let (method, params) = op.rc_call(true).unwrap();
+        assert_eq!(method, "sync/copy");
+        assert_eq!(params, json!({ "srcFs": "drive:photos", "dstFs": "/backup/photos" }));
+
+        let (method, params) = op.rc_call(false).unwrap();
+        assert_eq!(method, "operations/copyfile");
+        assert_eq!(params["srcRemote"], "photos");
+
+        let op = FileOp::Move { source: "relative/dir".to_string(), destination: "drive:dir".to_string() };
+        assert!(op.rc_call(true).is_none());
+    }
+
+    #[test]
+    fn rejects_names_that_leave_the_directory() {
+        for name in ["", "x/y", ".", ".."] {
+            assert!(renamed_path("drive:docs/a.txt", name).is_err(), "{:?} accepted", name);
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+use crate::{rclone_config, runner};
+
+// Which files a job or mount sees, written to a `--filter-from` file plus a few flags
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct FilterRules {
+    // Raw filter file lines in rclone syntax, "+ pattern", "- pattern" or "!", applied first
+    pub filter: Vec<String>,
+    pub exclude: Vec<String>,
+    // Once anything is included, everything else is excluded, as with --include
+    pub include: Vec<String>,
+    // Size with optional suffix, e.g. "100k", "1.5G"
+    pub min_size: Option<String>,
+    pub max_size: Option<String>,
+    // Duration or date, e.g. "2d", "1w", "2024-01-31"
+    pub min_age: Option<String>,
+    pub max_age: Option<String>,
+    // Skip directories containing one of these files, e.g. ".nobackup"
+    pub exclude_if_present: Vec<String>,
+}
+
+// Result of test_filters
+#[derive(Serialize)]
+pub struct FilterTest {
+    pub included: Vec<String>,
+    // Only known when testing sample paths
+    pub excluded: Vec<String>,
+}
+
+impl FilterRules {
+    pub fn is_empty(&self) -> bool {
+        *self == FilterRules::default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for line in &self.filter {
+            let line = line.trim();
+            let valid = line == "!" || line.starts_with('#') || line.starts_with("+ ") || line.starts_with("- ");
+            if !valid {
+                return Err(format!("'{}' is not a filter rule, rules start with '+ ', '- ' or are '!'", line));
+            }
+        }
+        for pattern in self.exclude.iter().chain(&self.include).chain(&self.exclude_if_present) {
+            if pattern.trim().is_empty() || pattern.contains('\n') {
+                return Err(format!("'{}' is not a valid pattern", pattern));
+            }
+        }
+        for size in self.min_size.iter().chain(&self.max_size) {
+            if !is_size(size) {
+                return Err(format!("'{}' is not a size, use e.g. 100k, 10M or 1.5G", size));
+            }
+        }
+        for age in self.min_age.iter().chain(&self.max_age) {
+            if age.trim().is_empty() || age.contains(char::is_whitespace) {
+                return Err(format!("'{}' is not an age, use e.g. 2d, 1w or 2024-01-31", age));
+            }
+        }
+        Ok(())
+    }
+
+    // The contents of the `--filter-from` file
+    pub fn filter_file(&self) -> String {
+        let mut lines: Vec<String> = self.filter.iter().map(|line| line.trim().to_string()).collect();
+        lines.extend(self.exclude.iter().map(|pattern| format!("- {}", pattern.trim())));
+        lines.extend(self.include.iter().map(|pattern| format!("+ {}", pattern.trim())));
+        if !self.include.is_empty() {
+            lines.push("- **".to_string());
+        }
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    // rclone flags for the rules, reading the filter lines from `filter_path`
+    pub fn args(&self, filter_path: Option<&Path>) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(filter_path) = filter_path {
+            args.push("--filter-from".to_string());
+            args.push(filter_path.to_string_lossy().to_string());
+        }
+        let flags = [
+            ("--min-size", &self.min_size),
+            ("--max-size", &self.max_size),
+            ("--min-age", &self.min_age),
+            ("--max-age", &self.max_age),
+        ];
+        for (flag, value) in flags {
+            if let Some(value) = value {
+                args.push(format!("{}={}", flag, value.trim()));
+            }
+        }
+        for name in &self.exclude_if_present {
+            args.push(format!("--exclude-if-present={}", name.trim()));
+        }
+        args
+    }
+}
+
+// "100", "100k", "1.5G", "10MiB"
+pub fn is_size(size: &str) -> bool {
+    let size = size.trim().to_lowercase();
+    let number_end = size.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(size.len());
+    let (number, unit) = size.split_at(number_end);
+    let unit = unit.trim_end_matches('b').trim_end_matches('i');
+    number.parse::<f64>().is_ok() && (unit.is_empty() || (unit.len() == 1 && "kmgtpe".contains(unit)))
+}
+
+// $XDG_CONFIG_HOME/de_rclone/filters, filter files stay around for systemd timers and crontab
+fn filters_dir() -> Result<PathBuf, String> {
+    dirs::config_dir()
+        .map(|dir| dir.join("de_rclone").join("filters"))
+        .ok_or_else(|| "Could not determine the config directory".to_string())
+}
+
+// Write the filter file for a job or mount, e.g. "job-123".
+// No filter file is written when there are no filter lines.
+fn write_filter_file(name: &str, rules: &FilterRules) -> Result<Option<PathBuf>, String> {
+    let content = rules.filter_file();
+    if content.is_empty() {
+        remove_filter_file(name);
+        return Ok(None);
+    }
+
+    let dir = filters_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = dir.join(format!("{}.filter", name));
+    fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(Some(path))
+}
+
+// Write the filter file and return the rclone flags for the rules
+pub fn write_args(name: &str, rules: &FilterRules) -> Result<Vec<String>, String> {
+    let path = write_filter_file(name, rules)?;
+    Ok(rules.args(path.as_deref()))
+}
+
+// Write the filter file and return the rules as the `_filter` parameter of an rc call,
+// None when there is nothing to filter
+pub fn write_rc_filter(name: &str, rules: &FilterRules) -> Result<Option<serde_json::Value>, String> {
+    if rules.is_empty() {
+        return Ok(None);
+    }
+
+    let mut filter = serde_json::Map::new();
+    if let Some(path) = write_filter_file(name, rules)? {
+        filter.insert("FilterFrom".to_string(), json!([path]));
+    }
+    let options = [
+        ("MinSize", &rules.min_size),
+        ("MaxSize", &rules.max_size),
+        ("MinAge", &rules.min_age),
+        ("MaxAge", &rules.max_age),
+    ];
+    for (option, value) in options {
+        if let Some(value) = value {
+            filter.insert(option.to_string(), json!(value.trim()));
+        }
+    }
+    if !rules.exclude_if_present.is_empty() {
+        filter.insert("ExcludeFile".to_string(), json!(rules.exclude_if_present));
+    }
+    Ok(Some(serde_json::Value::Object(filter)))
+}
+
+pub fn remove_filter_file(name: &str) {
+    if let Ok(dir) = filters_dir() {
+        let _ = fs::remove_file(dir.join(format!("{}.filter", name)));
+    }
+}
+
+// $XDG_CONFIG_HOME/de_rclone/mount_filters.json, filter rules per config file and remote
+fn mount_filters_path() -> Result<PathBuf, String> {
+    dirs::config_dir()
+        .map(|dir| dir.join("de_rclone").join("mount_filters.json"))
+        .ok_or_else(|| "Could not determine the config directory".to_string())
+}
+
+// Rules by config path, then remote name
+type MountFilters = HashMap<String, HashMap<String, FilterRules>>;
+
+#[derive(Serialize, Deserialize)]
+struct MountFiltersFile {
+    version: u32,
+    configs: MountFilters,
+}
+
+fn read_mount_filters() -> Result<MountFilters, String> {
+    let path = mount_filters_path()?;
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let file: MountFiltersFile = serde_json::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    Ok(file.configs)
+}
+
+pub fn mount_filters(config_path: &Path, remote_name: &str) -> Result<FilterRules, String> {
+    Ok(read_mount_filters()?
+        .get_mut(config_path.to_string_lossy().as_ref())
+        .and_then(|remotes| remotes.remove(remote_name))
+        .unwrap_or_default())
+}
+
+// Empty rules remove the remote's entry
+pub fn set_mount_filters(config_path: &Path, remote_name: &str, rules: FilterRules) -> Result<(), String> {
+    rules.validate()?;
+
+    let mut all = read_mount_filters()?;
+    let config_key = config_path.to_string_lossy().to_string();
+    if rules.is_empty() {
+        if let Some(remotes) = all.get_mut(&config_key) {
+            remotes.remove(remote_name);
+            if remotes.is_empty() {
+                all.remove(&config_key);
+            }
+        }
+        remove_filter_file(&mount_filter_name(config_path, remote_name));
+    } else {
+        all.entry(config_key).or_default().insert(remote_name.to_string(), rules);
+    }
+
+    let path = mount_filters_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let file = MountFiltersFile { version: 1, configs: all };
+    let content = serde_json::to_string_pretty(&file).map_err(|e| format!("Failed to serialize mount filters: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+pub fn mount_filter_name(config_path: &Path, remote_name: &str) -> String {
+    format!("mount-{}", rclone_config::remote_key(config_path, remote_name))
+}
+
+// See which files the rules let through. With `sample_paths` the paths are created as empty
+// files in a temporary directory, so size and age rules don't mean much there. Otherwise
+// `target` ("remote:path") is listed.
+pub async fn test(rules: &FilterRules, sample_paths: &[String], target: Option<&str>, config_path: &Path, operation_id: Option<String>) -> Result<FilterTest, String> {
+    rules.validate()?;
+
+    let work_dir = std::env::temp_dir().join(runner::generate_id("de_rclone-filters"));
+    let root = work_dir.join("root");
+    let result = async {
+        fs::create_dir_all(&root).map_err(|e| format!("Failed to create {}: {}", root.display(), e))?;
+
+        let mut samples = Vec::new();
+        for sample in sample_paths {
+            let sample = sample.trim().trim_start_matches('/');
+            if sample.is_empty() || sample.split('/').any(|part| part == "..") {
+                return Err(format!("'{}' is not a relative path", sample));
+            }
+            let path = root.join(sample);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+            }
+            fs::write(&path, "").map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+            samples.push(sample.to_string());
+        }
+
+        let listed = if samples.is_empty() {
+            target.ok_or("Give sample paths or a target to test the filters against")?.to_string()
+        } else {
+            root.to_string_lossy().to_string()
+        };
+
+        let filter_path = work_dir.join("rules.filter");
+        fs::write(&filter_path, rules.filter_file())
+            .map_err(|e| format!("Failed to write {}: {}", filter_path.display(), e))?;
+
+        let mut cmd = Command::new("rclone");
+        cmd.args(["lsf", "-R", "--files-only"])
+            .arg(&listed)
+            .args(rules.args(Some(&filter_path)))
+            .arg("--config")
+            .arg(config_path);
+
+        let output = runner::output("list", operation_id, cmd).await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Failed to test filters: {}", stderr.trim()));
+        }
+
+        let included: Vec<String> = String::from_utf8_lossy(&output.stdout).lines().map(String::from).collect();
+        let excluded = samples.into_iter().filter(|sample| !included.contains(sample)).collect();
+        Ok(FilterTest { included, excluded })
+    }
+    .await;
+
+    let _ = fs::remove_dir_all(&work_dir);
+    result
+}
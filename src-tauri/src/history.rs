@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::bisync::BisyncConflict;
+
+// Older runs of a job are deleted once it has more than this
+const MAX_RUNS_PER_JOB: usize = 200;
+
+pub const DEFAULT_RUN_LIMIT: usize = 50;
+
+// One run of a job as kept in the history
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct JobRun {
+    pub id: String,
+    pub job_id: String,
+    pub job_name: String,
+    // "manual" or "scheduled"
+    pub trigger: String,
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub success: bool,
+    // None when rclone was killed, e.g. on cancellation or timeout
+    pub exit_code: Option<i32>,
+    pub bytes_transferred: u64,
+    pub files_transferred: u64,
+    pub checks: u64,
+    pub deletes: u64,
+    pub error_count: u64,
+    pub errors: Vec<String>,
+    // Files both sides changed, bisync jobs only
+    pub conflicts: Vec<BisyncConflict>,
+    // The rclone log of the run without the periodic stats
+    pub log_path: Option<PathBuf>,
+}
+
+// $XDG_DATA_HOME/de_rclone/runs (~/.local/share/de_rclone/runs), one JSON file and one log per run
+pub fn runs_dir() -> Result<PathBuf, String> {
+    dirs::data_dir()
+        .map(|dir| dir.join("de_rclone").join("runs"))
+        .ok_or_else(|| "Could not determine the data directory".to_string())
+}
+
+// Save a run and its log, then drop the oldest runs of the job beyond MAX_RUNS_PER_JOB
+pub fn record(mut run: JobRun, log: &str) -> Result<JobRun, String> {
+    let dir = runs_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    if !log.is_empty() {
+        let log_path = dir.join(format!("{}.log", run.id));
+        fs::write(&log_path, log).map_err(|e| format!("Failed to write {}: {}", log_path.display(), e))?;
+        run.log_path = Some(log_path);
+    }
+
+    let run_path = dir.join(format!("{}.json", run.id));
+    let content = serde_json::to_string_pretty(&run).map_err(|e| format!("Failed to serialize job run: {}", e))?;
+    fs::write(&run_path, content).map_err(|e| format!("Failed to write {}: {}", run_path.display(), e))?;
+
+    for old in read_runs(Some(&run.job_id))?.iter().skip(MAX_RUNS_PER_JOB) {
+        delete_run_files(old);
+    }
+
+    Ok(run)
+}
+
+// Runs newest first, of one job or of all jobs
+pub fn list(job_id: Option<&str>, limit: usize) -> Result<Vec<JobRun>, String> {
+    let mut runs = read_runs(job_id)?;
+    runs.truncate(limit);
+    Ok(runs)
+}
+
+pub fn get(run_id: &str) -> Result<JobRun, String> {
+    let run_path = runs_dir()?.join(format!("{}.json", run_id));
+    let content = fs::read_to_string(&run_path).map_err(|_| format!("Job run {} not found", run_id))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", run_path.display(), e))
+}
+
+// Forget all runs of a deleted job
+pub fn delete_job_runs(job_id: &str) -> Result<(), String> {
+    for run in read_runs(Some(job_id))? {
+        delete_run_files(&run);
+    }
+    Ok(())
+}
+
+fn read_runs(job_id: Option<&str>) -> Result<Vec<JobRun>, String> {
+    let dir = runs_dir()?;
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+
+    let mut runs: Vec<JobRun> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let content = fs::read_to_string(&path).ok()?;
+            match serde_json::from_str::<JobRun>(&content) {
+                Ok(run) => Some(run),
+                Err(e) => {
+                    eprintln!("Skipping broken job run {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .filter(|run| job_id.is_none_or(|job_id| run.job_id == job_id))
+        .collect();
+
+    runs.sort_by(|a, b| b.started_at.cmp(&a.started_at).then_with(|| b.id.cmp(&a.id)));
+    Ok(runs)
+}
+
+fn delete_run_files(run: &JobRun) {
+    if let Ok(dir) = runs_dir() {
+        let _ = fs::remove_file(dir.join(format!("{}.json", run.id)));
+    }
+    if let Some(log_path) = &run.log_path {
+        let _ = fs::remove_file(log_path);
+    }
+}
@@ -0,0 +1,673 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+use tokio::process::Command;
+
+use crate::scheduler::Schedule;
+use crate::transfer::{self, TransferStats};
+use crate::bisync::{self, BisyncReport};
+use crate::filters::{self, FilterRules};
+use crate::history::{self, JobRun};
+use crate::{bandwidth, file_ops, profiles, rc, rclone_config, rcd, runner, CommandResult};
+
+const JOBS_FILE_VERSION: u32 = 1;
+
+// Serializes read-modify-write cycles of the jobs file
+static JOBS_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum JobMode {
+    #[default]
+    Sync,
+    Copy,
+    Move,
+    Bisync,
+}
+
+impl JobMode {
+    // The rclone subcommand for this mode
+    pub fn command(&self) -> &'static str {
+        match self {
+            JobMode::Sync => "sync",
+            JobMode::Copy => "copy",
+            JobMode::Move => "move",
+            JobMode::Bisync => "bisync",
+        }
+    }
+}
+
+// A saved transfer between two rclone paths, e.g. a nightly "sync ~/Photos gdrive:Photos"
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Job {
+    // Assigned on creation, ignored in create_job
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub source: String,
+    pub destination: String,
+    #[serde(default)]
+    pub mode: JobMode,
+    #[serde(default)]
+    pub filters: FilterRules,
+    // Extra rclone flags passed as they are, e.g. "--transfers=8"
+    #[serde(default)]
+    pub flags: Vec<String>,
+    // Run automatically while the app is open, see scheduler.rs
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
+    // The rclone config the job's remotes are in, may start with "~/". Set to the active
+    // profile's on creation so scheduled runs don't follow later profile switches.
+    #[serde(default)]
+    pub config_path: Option<String>,
+    #[serde(default)]
+    pub created_at: u64,
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+impl Job {
+    // The job's own config, for jobs saved before they had one the given or active profile's
+    pub fn resolve_config_path(&self, config_path_opt: Option<String>) -> Result<PathBuf, String> {
+        rclone_config::resolve_config_path(self.config_path.clone().or(config_path_opt))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct JobsFile {
+    version: u32,
+    #[serde(default)]
+    jobs: Vec<Job>,
+}
+
+// Flags that would point rclone somewhere else than the job says, filters go in FilterRules
+const RESERVED_FLAGS: &[&str] = &["--config", "--filter", "--filter-from"];
+
+// $XDG_CONFIG_HOME/de_rclone/jobs.json (~/.config/de_rclone/jobs.json)
+pub fn jobs_path() -> Result<PathBuf, String> {
+    dirs::config_dir()
+        .map(|dir| dir.join("de_rclone").join("jobs.json"))
+        .ok_or_else(|| "Could not determine the config directory".to_string())
+}
+
+fn read_jobs(path: &Path) -> Result<Vec<Job>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let file: JobsFile = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+
+    if file.version > JOBS_FILE_VERSION {
+        return Err(format!(
+            "{} was written by a newer de_rclone (version {}, supported {})",
+            path.display(),
+            file.version,
+            JOBS_FILE_VERSION
+        ));
+    }
+
+    Ok(file.jobs)
+}
+
+// Written to a temporary file first so a crash never leaves a half written jobs file
+fn write_jobs(path: &Path, jobs: Vec<Job>) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+
+    let file = JobsFile { version: JOBS_FILE_VERSION, jobs };
+    let content = serde_json::to_string_pretty(&file)
+        .map_err(|e| format!("Failed to serialize jobs: {}", e))?;
+
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+    fs::rename(&tmp_path, path)
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+// Load, change and save the jobs file while holding the lock
+fn modify_jobs<T>(change: impl FnOnce(&mut Vec<Job>) -> Result<T, String>) -> Result<T, String> {
+    let _guard = JOBS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = jobs_path()?;
+    let mut jobs = read_jobs(&path)?;
+    let result = change(&mut jobs)?;
+    write_jobs(&path, jobs)?;
+    Ok(result)
+}
+
+fn validate(job: &Job) -> Result<(), String> {
+    if job.name.trim().is_empty() {
+        return Err("Job name cannot be empty".to_string());
+    }
+    // The name ends up in exported systemd units, where a line break would start a new directive
+    if job.name.contains(char::is_control) {
+        return Err("Job name cannot contain line breaks or other control characters".to_string());
+    }
+    if job.source.trim().is_empty() || job.destination.trim().is_empty() {
+        return Err("Job source and destination cannot be empty".to_string());
+    }
+    if job.source.trim_end_matches('/') == job.destination.trim_end_matches('/') {
+        return Err("Job source and destination must differ".to_string());
+    }
+    if job.config_path.as_ref().is_some_and(|path| path.trim().is_empty()) {
+        return Err("Job config path cannot be empty".to_string());
+    }
+
+    if let Some(schedule) = &job.schedule {
+        schedule.validate()?;
+    }
+    job.filters.validate()?;
+
+    for flag in &job.flags {
+        if !flag.starts_with('-') {
+            return Err(format!("'{}' is not a flag", flag));
+        }
+        let name = flag.split('=').next().unwrap_or_default();
+        if RESERVED_FLAGS.contains(&name) {
+            return Err(format!("{} is set by de_rclone and cannot be used as a job flag", name));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn list() -> Result<Vec<Job>, String> {
+    let _guard = JOBS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    read_jobs(&jobs_path()?)
+}
+
+pub fn find(job_id: &str) -> Result<Job, String> {
+    list()?
+        .into_iter()
+        .find(|job| job.id == job_id)
+        .ok_or_else(|| format!("Job {} not found", job_id))
+}
+
+pub fn create(job: Job) -> Result<Job, String> {
+    validate(&job)?;
+    let now = runner::now_secs();
+    let job = Job {
+        id: runner::generate_id("job"),
+        config_path: job.config_path.clone().or_else(|| profiles::active().map(|profile| profile.config_path)),
+        created_at: now,
+        updated_at: now,
+        ..job
+    };
+
+    modify_jobs(|jobs| {
+        if jobs.iter().any(|existing| existing.name == job.name) {
+            return Err(format!("A job named '{}' already exists", job.name));
+        }
+        jobs.push(job.clone());
+        Ok(job)
+    })
+}
+
+pub fn update(job: Job) -> Result<Job, String> {
+    validate(&job)?;
+
+    modify_jobs(|jobs| {
+        if jobs.iter().any(|existing| existing.name == job.name && existing.id != job.id) {
+            return Err(format!("A job named '{}' already exists", job.name));
+        }
+        let existing = jobs
+            .iter_mut()
+            .find(|existing| existing.id == job.id)
+            .ok_or_else(|| format!("Job {} not found", job.id))?;
+
+        *existing = Job {
+            config_path: job.config_path.clone().or(existing.config_path.take()),
+            created_at: existing.created_at,
+            updated_at: runner::now_secs(),
+            ..job
+        };
+        Ok(existing.clone())
+    })
+}
+
+pub fn delete(job_id: &str) -> Result<(), String> {
+    modify_jobs(|jobs| {
+        let before = jobs.len();
+        jobs.retain(|job| job.id != job_id);
+        if jobs.len() == before {
+            return Err(format!("Job {} not found", job_id));
+        }
+        filters::remove_filter_file(job_id);
+        Ok(())
+    })
+}
+
+// rclone arguments for a job, without the program name.
+// Writes the job's filter file, which has to stay in place for exported systemd timers.
+// The bandwidth limit of the remotes involved applies unless the job sets its own --bwlimit.
+pub fn args(job: &Job, config_path: &Path) -> Result<Vec<String>, String> {
+    let mut args = vec![
+        job.mode.command().to_string(),
+        job.source.clone(),
+        job.destination.clone(),
+        "--config".to_string(),
+        config_path.to_string_lossy().to_string(),
+    ];
+    args.extend(filters::write_args(&job.id, &job.filters)?);
+    if !job.flags.iter().any(|flag| flag.starts_with("--bwlimit")) {
+        args.extend(bandwidth::load()?.args(&[&job.destination, &job.source]));
+    }
+    args.extend(job.flags.iter().cloned());
+    Ok(args)
+}
+
+// Progress event emitted to the UI while a job runs
+#[derive(Serialize, Clone)]
+pub struct JobProgress {
+    pub job_id: String,
+    pub operation_id: String,
+    pub stats: TransferStats,
+}
+
+// Run a job to completion, handing rclone's stats to `on_progress` every second.
+// Every run ends up in the history, including ones that were cancelled or timed out.
+// The operation id defaults to "job-run-<job id>" so the same job can't run twice at once
+// and the UI can cancel it without knowing a generated id.
+// `resync` only applies to bisync jobs, it is needed on the first run and after critical errors.
+pub async fn run(
+    job: &Job,
+    config_path: &Path,
+    operation_id: Option<String>,
+    trigger: &str,
+    resync: bool,
+    mut on_progress: impl FnMut(JobProgress),
+) -> Result<CommandResult, String> {
+    let operation_id = operation_id.unwrap_or_else(|| format!("job-run-{}", job.id));
+    let operation = runner::Operation::start("transfer", Some(operation_id.clone()))?;
+
+    let bisync = job.mode == JobMode::Bisync;
+    let mut job_run = JobRun {
+        id: runner::generate_id("run"),
+        job_id: job.id.clone(),
+        job_name: job.name.clone(),
+        trigger: trigger.to_string(),
+        started_at: runner::now_secs(),
+        ..JobRun::default()
+    };
+
+    let started = Instant::now();
+    let mut last_stats = None;
+    let mut progress = |stats: TransferStats| {
+        last_stats = Some(stats.clone());
+        on_progress(JobProgress {
+            job_id: job.id.clone(),
+            operation_id: operation_id.clone(),
+            stats,
+        });
+    };
+
+    // Extra rclone flags have no rc equivalent and bisync takes its own options, those
+    // jobs always run through the CLI
+    let rcd = rcd::active_for(config_path).filter(|_| !bisync && job.flags.is_empty());
+    let (command, result) = match rcd {
+        Some(endpoint) => (
+            format!("rc {}", rc_method(job.mode)),
+            run_rc(job, &endpoint, &operation, &mut progress).await,
+        ),
+        None => {
+            let mut args = args(job, config_path)?;
+            if bisync && resync {
+                args.push("--resync".to_string());
+            }
+            (
+                format!("rclone {}", args.join(" ")),
+                run_cli(job, &args, &operation, &operation_id, &mut progress).await,
+            )
+        }
+    };
+    let duration_ms = started.elapsed().as_millis();
+
+    file_ops::invalidate_caches(&job.destination);
+    if job.mode != JobMode::Copy {
+        file_ops::invalidate_caches(&job.source);
+    }
+
+    job_run.finished_at = runner::now_secs();
+    if let Some(stats) = last_stats {
+        job_run.bytes_transferred = stats.bytes;
+        job_run.files_transferred = stats.transfers;
+        job_run.checks = stats.checks;
+        job_run.deletes = stats.deletes;
+        job_run.error_count = stats.errors;
+    }
+
+    let outcome = match result {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            job_run.errors = vec![e.clone()];
+            if let Err(record_error) = history::record(job_run, "") {
+                eprintln!("Failed to record run of job '{}': {}", job.name, record_error);
+            }
+            return Err(e);
+        }
+    };
+
+    job_run.success = outcome.success;
+    job_run.exit_code = outcome.exit_code;
+    job_run.errors = outcome.errors;
+
+    let bisync_report = bisync.then(|| BisyncReport {
+        needs_resync: !job_run.success && bisync::needs_resync(&outcome.log),
+        resynced: resync,
+        recovered_lock: outcome.recovered_lock,
+        conflicts: bisync::conflicts(&outcome.log, &job.source, &job.destination),
+    });
+    if let Some(report) = &bisync_report {
+        job_run.conflicts = report.conflicts.clone();
+    }
+
+    let job_run = match history::record(job_run.clone(), &outcome.log) {
+        Ok(recorded) => recorded,
+        Err(e) => {
+            eprintln!("Failed to record run of job '{}': {}", job.name, e);
+            job_run
+        }
+    };
+
+    let details = json!({
+        "job_id": job.id,
+        "run_id": job_run.id,
+        "command": command,
+        "exit_code": job_run.exit_code,
+        "duration_ms": duration_ms,
+        "log": transfer::log_messages(&outcome.log),
+        "errors": job_run.errors,
+        "bisync": bisync_report,
+    });
+
+    let message = match &bisync_report {
+        Some(report) if report.needs_resync => {
+            format!("Job '{}' has no bisync listings yet, run it once with resync", job.name)
+        }
+        Some(report) if job_run.success && !report.conflicts.is_empty() => {
+            format!("Job '{}' finished with {} conflicts", job.name, report.conflicts.len())
+        }
+        _ if job_run.success => format!("Job '{}' finished", job.name),
+        _ => format!("Job '{}' failed", job.name),
+    };
+
+    Ok(CommandResult {
+        success: job_run.success,
+        message,
+        details: Some(details),
+    })
+}
+
+// How a run ended, whichever backend ran it
+struct RunOutcome {
+    success: bool,
+    // None when run through rc
+    exit_code: Option<i32>,
+    // rclone's JSON log without the periodic stats, empty when run through rc
+    log: String,
+    errors: Vec<String>,
+    recovered_lock: Option<PathBuf>,
+}
+
+async fn run_cli(
+    job: &Job,
+    args: &[String],
+    operation: &runner::Operation,
+    operation_id: &str,
+    progress: &mut impl FnMut(TransferStats),
+) -> Result<RunOutcome, String> {
+    // Serve rc while running so bandwidth limits can be changed on the fly. It's registered
+    // once rclone logs the port it bound.
+    let credentials = rc::RcEndpoint::local()?;
+    let _registration = rc::Registration(operation_id.to_string());
+
+    let mut recovered_lock = None;
+    loop {
+        let mut cmd = Command::new("rclone");
+        cmd.args(args).args(transfer::JSON_LOG_FLAGS).arg("--rc");
+        credentials.serve(&mut cmd);
+
+        let output = operation
+            .output_streaming(cmd, |line| {
+                if let Some(url) = rc::serving_url(line) {
+                    rc::register(operation_id, credentials.with_url(url), vec![job.destination.clone(), job.source.clone()]);
+                }
+                match transfer::parse_log_line(line).and_then(|entry| entry.stats) {
+                    Some(stats) => {
+                        progress(stats);
+                        false
+                    }
+                    None => true,
+                }
+            })
+            .await?;
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+        // A bisync that crashed leaves its lock file behind and every later run refuses to
+        // start. Remove it once if its owner is gone and try again.
+        if job.mode == JobMode::Bisync && !output.status.success() && recovered_lock.is_none() {
+            if let Some(lock) = bisync::blocking_lock(&stderr) {
+                match bisync::remove_stale_lock(&lock) {
+                    Ok(()) => {
+                        recovered_lock = Some(lock);
+                        continue;
+                    }
+                    Err(e) => eprintln!("Not removing bisync lock {}: {}", lock.display(), e),
+                }
+            }
+        }
+
+        return Ok(RunOutcome {
+            success: output.status.success(),
+            exit_code: output.status.code(),
+            errors: transfer::error_messages(&stderr),
+            log: stderr,
+            recovered_lock,
+        });
+    }
+}
+
+fn rc_method(mode: JobMode) -> &'static str {
+    match mode {
+        JobMode::Sync => "sync/sync",
+        JobMode::Copy => "sync/copy",
+        JobMode::Move => "sync/move",
+        JobMode::Bisync => "sync/bisync",
+    }
+}
+
+// Run a sync, copy or move as an async job of the rcd daemon
+async fn run_rc(job: &Job, endpoint: &rc::RcEndpoint, operation: &runner::Operation, progress: &mut impl FnMut(TransferStats)) -> Result<RunOutcome, String> {
+    let mut params = json!({
+        "srcFs": job.source,
+        "dstFs": job.destination,
+        "_async": true,
+    });
+    if let Some(filter) = filters::write_rc_filter(&job.id, &job.filters)? {
+        params["_filter"] = filter;
+    }
+
+    let reply = endpoint.call(rc_method(job.mode), params, None).await?;
+    let rc_job_id = reply
+        .get("jobid")
+        .and_then(serde_json::Value::as_i64)
+        .ok_or("rclone rcd did not return a job id")?;
+
+    let status = rcd::wait_for_job(endpoint, rc_job_id, operation, |stats| {
+        if let Ok(stats) = serde_json::from_value(stats) {
+            progress(stats);
+        }
+    })
+    .await?;
+
+    let error = status.get("error").and_then(serde_json::Value::as_str).unwrap_or_default();
+    Ok(RunOutcome {
+        success: status.get("success").and_then(serde_json::Value::as_bool).unwrap_or(false),
+        exit_code: None,
+        log: String::new(),
+        errors: if error.is_empty() { Vec::new() } else { vec![error.to_string()] },
+        recovered_lock: None,
+    })
+}
+
+// Entries per list in a preview, the counts are always complete
+const PREVIEW_LIST_LIMIT: usize = 1000;
+
+#[derive(Serialize, Clone)]
+pub struct PreviewEntry {
+    pub path: String,
+    pub size: Option<i64>,
+}
+
+#[derive(Serialize, Default)]
+pub struct PreviewCounts {
+    pub created: u64,
+    pub updated: u64,
+    pub deleted: u64,
+    pub unchanged: u64,
+    pub errors: u64,
+}
+
+// What running a job would change, from a `--dry-run`
+#[derive(Serialize)]
+pub struct JobPreview {
+    pub job_id: String,
+    pub mode: JobMode,
+    pub counts: PreviewCounts,
+    // Files missing on the destination
+    pub created: Vec<PreviewEntry>,
+    // Files on both sides that differ
+    pub updated: Vec<PreviewEntry>,
+    // Files only on the destination, removed by a sync
+    pub deleted: Vec<PreviewEntry>,
+    pub bytes_to_transfer: u64,
+    pub errors: Vec<String>,
+    // Set when a list was cut off at PREVIEW_LIST_LIMIT entries
+    pub truncated: bool,
+}
+
+// Run a job with `--dry-run` and collect what it would copy, update and delete.
+// rclone's `--combined` report tells new files from changed ones, the JSON log adds sizes.
+pub async fn preview(job: &Job, config_path: &Path, operation_id: Option<String>) -> Result<JobPreview, String> {
+    if job.mode == JobMode::Bisync {
+        return Err("Previews are not available for bisync jobs".to_string());
+    }
+
+    let operation = runner::Operation::start("preview", operation_id)?;
+
+    let mut cmd = Command::new("rclone");
+    cmd.args(args(job, config_path)?)
+        .args(["--dry-run", "--use-json-log", "--combined", "-"]);
+
+    let output = operation.output(cmd).await?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    let mut sizes = HashMap::new();
+    let mut errors = Vec::new();
+    for line in stderr.lines() {
+        match transfer::parse_log_line(line) {
+            Some(entry) if !entry.skipped.is_empty() => {
+                if let Some(size) = entry.size {
+                    sizes.insert(entry.object, size);
+                }
+            }
+            Some(entry) if entry.level == "error" || entry.level == "critical" => {
+                errors.push(transfer::log_messages(line).join(""));
+            }
+            Some(_) => {}
+            None if line.trim().is_empty() => {}
+            None => errors.push(line.to_string()),
+        }
+    }
+
+    if !output.status.success() && errors.is_empty() {
+        return Err(format!("Preview of job '{}' failed", job.name));
+    }
+
+    let mut preview = JobPreview {
+        job_id: job.id.clone(),
+        mode: job.mode,
+        counts: PreviewCounts::default(),
+        created: Vec::new(),
+        updated: Vec::new(),
+        deleted: Vec::new(),
+        bytes_to_transfer: 0,
+        errors,
+        truncated: false,
+    };
+
+    // "+ path" missing on the destination, "* path" differs, "- path" missing on the source,
+    // "= path" identical, "! path" error
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for line in stdout.lines() {
+        let Some((symbol, path)) = line.split_once(' ') else {
+            continue;
+        };
+        let entry = PreviewEntry {
+            path: path.to_string(),
+            size: sizes.get(path).copied(),
+        };
+
+        let list = match symbol {
+            "+" => {
+                preview.counts.created += 1;
+                &mut preview.created
+            }
+            "*" => {
+                preview.counts.updated += 1;
+                &mut preview.updated
+            }
+            // Only sync removes files that are gone from the source
+            "-" if job.mode == JobMode::Sync => {
+                preview.counts.deleted += 1;
+                &mut preview.deleted
+            }
+            "=" => {
+                preview.counts.unchanged += 1;
+                continue;
+            }
+            "!" => {
+                preview.counts.errors += 1;
+                continue;
+            }
+            _ => continue,
+        };
+
+        if symbol != "-" {
+            preview.bytes_to_transfer += entry.size.unwrap_or(0).max(0) as u64;
+        }
+        if list.len() < PREVIEW_LIST_LIMIT {
+            list.push(entry);
+        } else {
+            preview.truncated = true;
+        }
+    }
+
+    Ok(preview)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(name: &str) -> Job {
+        serde_json::from_value(json!({ "name": name, "source": "/home/me/Photos", "destination": "gdrive:Photos" })).unwrap()
+    }
+
+    #[test]
+    fn rejects_control_characters_in_names() {
+        assert!(validate(&job("Nightly photos")).is_ok());
+        for name in ["Nightly\nExecStartPre=/bin/sh -c evil", "Nightly\r", "tab\tname"] {
+            assert!(validate(&job(name)).is_err(), "{:?} accepted", name);
+        }
+    }
+}
@@ -4,11 +4,51 @@ use serde::{Deserialize, Serialize};
 use std::process::Command;
 use std::fs;
 use std::io::Write;
-use tauri::{Manager};
+use std::os::unix::fs::OpenOptionsExt;
+use tauri::{Emitter, Manager};
 use tauri_plugin_dialog::DialogExt;
-use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+mod bandwidth;
+mod bisync;
+mod browser;
+mod dependencies;
+mod diagnostics;
+mod file_ops;
+mod filters;
+mod history;
+mod jobs;
+mod oauth;
+mod plugins;
+mod profiles;
+mod rc;
+mod rcd;
+mod rclone_config;
+mod runner;
+mod scheduler;
+mod settings;
+mod transfer;
+mod usage;
+mod wizard;
+mod wrappers;
+
+use bandwidth::BandwidthSettings;
+use browser::DirListing;
+use dependencies::RemoteGraph;
+use diagnostics::DiagnosticsReport;
+use file_ops::FileOp;
+use filters::{FilterRules, FilterTest};
+use history::JobRun;
+use jobs::{Job, JobPreview};
+use profiles::{Profile, ProfileList};
+use rcd::{BackendKind, BackendStatus, MountInfo, RcJob};
+use runner::{OperationInfo, Timeouts};
+use scheduler::ScheduledJob;
+use settings::Settings;
+use usage::RemoteUsage;
+use plugins::{PluginDir, PluginField, PluginRegistry, PluginValidation};
+use wizard::{Backend, ConfigQuestion};
+
 #[derive(Serialize, Deserialize)]
 struct Remote {
     name: String,
@@ -16,144 +56,86 @@ struct Remote {
     mounted: String,
     cron: String,
     mount_point: String,
+    // "remote:path" targets of wrapper remotes like crypt, empty for plain remotes
+    #[serde(default)]
+    wraps: Vec<String>,
+    // Only filled in when asked for, see get_remotes
+    #[serde(default)]
+    usage: Option<RemoteUsage>,
 }
 
 #[derive(Serialize)]
 struct CommandResult {
     success: bool,
     message: String,
+    // Structured extras for the UI, e.g. what a file operation did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<serde_json::Value>,
 }
 
-// Plugin structure definition
-#[derive(Serialize, Deserialize, Clone)]
-struct PluginField {
-    name: String,
-    display_name: String,
-    #[serde(rename = "type")]
-    field_type: String,
-    required: bool,
-    #[serde(default)]
-    default: String,
-    #[serde(default)]
-    placeholder: String,
-    #[serde(default)]
-    tooltip: String,
-}
-
-#[derive(Serialize, Deserialize)]
-struct Plugin {
-    name: String,
-    display_name: String,
-    description: String,
-    version: String,
-    author: String,
-    #[serde(default)]
-    basic_fields: Vec<PluginField>,
-    #[serde(default)]
-    advanced_fields: Vec<PluginField>,
-    #[serde(default)]
-    validation: HashMap<String, serde_json::Value>,
-}
-
-// Get all rclone remotes
+// Get all rclone remotes.
+// With `include_usage` each remote also gets its quota, which may take a while on first load.
 #[tauri::command]
-async fn get_remotes(config_path_opt: Option<String>) -> Result<Vec<Remote>, String> {
-    // Use the provided config path or default to ~/.config/rclone/rclone.conf
-    let config_path = if let Some(path_str) = config_path_opt {
-        expand_tilde_path(&path_str)?
-    } else {
-        // Use the default path
-        let home_dir = std::env::var("HOME").map_err(|e| format!("HOME not set: {}", e))?;
-        std::path::PathBuf::from(&home_dir).join(".config").join("rclone").join("rclone.conf")
-    };
+async fn get_remotes(config_path_opt: Option<String>, include_usage: Option<bool>) -> Result<Vec<Remote>, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
 
     println!("Looking for config at path: {:?}", config_path); // Debug log
 
-    if !config_path.exists() {
-        return Err(format!("rclone.conf not found at {:?}", config_path));
-    }
-
-    // Read the config file to get remote names and types
-    let config_content = fs::read_to_string(&config_path)
-        .map_err(|e| format!("Failed to read config from {:?}: {}", config_path, e))?;
+    // Get remote names and types from the daemon serving this config, or the config file
+    let sections = config_sections(&config_path).await?;
 
-    println!("Config file content length: {}", config_content.len());
+    // Read once for all remotes, a missing crontab just means nothing is auto-mounted
+    let crontab = read_crontab().await.unwrap_or_default();
 
     let mut remotes = Vec::new();
-    let mut current_section = String::new();
-    let mut current_type = String::new();
-
-    for (_, line) in config_content.lines().enumerate() {
-        let line = line.trim();
-
-        // Skip empty lines and comments
-        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+    for section in sections {
+        // Only sections with a type are remotes
+        if section.remote_type().is_empty() {
             continue;
         }
 
-        // Check if this is a section header
-        if line.starts_with('[') && line.ends_with(']') {
-            // If we have a previous section with a type, add it to the list
-            if !current_section.is_empty() && !current_type.is_empty() {
-                let mount_point = get_mount_dir(&current_section);
-                let mounted = if is_mounted(&mount_point) { "Yes".to_string() } else { "No".to_string() };
-                let cron = if is_in_crontab(&current_section) { "Yes".to_string() } else { "No".to_string() };
-
-                println!("Adding remote: {} of type {}", current_section, current_type);
-
-                remotes.push(Remote {
-                    name: current_section,
-                    r#type: current_type,
-                    mounted,
-                    cron,
-                    mount_point,
-                });
-            }
-
-            // Start processing new section
-            current_section = line[1..line.len()-1].to_string();
-            current_type = String::new();
-        } else if line.starts_with("type =") || line.starts_with("type=") {
-            // Extract the type value
-            let parts: Vec<&str> = line.splitn(2, '=').collect();
-            if parts.len() > 1 {
-                current_type = parts[1].trim().to_string();
-                println!("Found type '{}' for section '{}'", current_type, current_section);
-            }
-        }
-    }
-
-    // Add the last section if it was a remote
-    if !current_section.is_empty() && !current_type.is_empty() {
-        let mount_point = get_mount_dir(&current_section);
-        let mounted = if is_mounted(&mount_point) { "Yes".to_string() } else { "No".to_string() };
-        let cron = if is_in_crontab(&current_section) { "Yes".to_string() } else { "No".to_string() };
+        let mount_point = get_mount_dir(&config_path, &section.name);
+        let mounted = if is_mounted(&mount_point).await { "Yes".to_string() } else { "No".to_string() };
+        let cron = if crontab.contains(&cron_mount_cmd(&section.name)) { "Yes".to_string() } else { "No".to_string() };
 
-        println!("Adding final remote: {} of type {}", current_section, current_type);
+        println!("Adding remote: {} of type {}", section.name, section.remote_type());
 
         remotes.push(Remote {
-            name: current_section,
-            r#type: current_type,
+            r#type: section.remote_type().to_string(),
+            wraps: section.upstream_targets(),
+            name: section.name,
             mounted,
             cron,
             mount_point,
+            usage: None,
         });
     }
 
+    if include_usage.unwrap_or(false) {
+        let names: Vec<String> = remotes.iter().map(|r| r.name.clone()).collect();
+        let ttl = std::time::Duration::from_secs(usage::DEFAULT_USAGE_TTL_SECS);
+        let mut usages = usage::remotes_usage(&names, &config_path, ttl).await;
+        for remote in &mut remotes {
+            remote.usage = usages.remove(&remote.name);
+        }
+    }
+
     println!("Found {} remotes total", remotes.len());
     Ok(remotes)
 }
 
 // Mount a remote
 #[tauri::command]
-async fn mount_remote(remote_name: String, config_path_opt: Option<String>) -> Result<CommandResult, String> {
-    let mount_point = get_mount_dir(&remote_name);
+async fn mount_remote(remote_name: String, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<CommandResult, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    let mount_point = get_mount_dir(&config_path, &remote_name);
 
     // Check if already mounted
-    if is_mounted(&mount_point) {
+    if is_mounted(&mount_point).await {
         return Ok(CommandResult {
             success: true,
             message: format!("{} is already mounted at {}", remote_name, mount_point),
+            details: None,
         });
     }
 
@@ -161,9 +143,20 @@ async fn mount_remote(remote_name: String, config_path_opt: Option<String>) -> R
     std::fs::create_dir_all(&mount_point)
         .map_err(|e| format!("Failed to create mount directory: {}", e))?;
 
+    // The rcd daemon serves the mount itself when it runs on this config, with its own global
+    // bandwidth limit. Attached daemons have no config of ours and never mount locally.
+    if let Some(endpoint) = rcd::active_for(&config_path) {
+        rcd::mount(&endpoint, Some(&config_path), &remote_name, &mount_point, operation_id).await?;
+        return Ok(CommandResult {
+            success: true,
+            message: format!("Successfully mounted {} at {} via rclone rcd", remote_name, mount_point),
+            details: None,
+        });
+    }
+
     // Execute rclone mount command
-    let mut cmd = Command::new("rclone");
-    cmd.args(&[
+    let mut cmd = tokio::process::Command::new("rclone");
+    cmd.args([
         "mount",
         &format!("{}:", remote_name),
         &mount_point,
@@ -171,61 +164,101 @@ async fn mount_remote(remote_name: String, config_path_opt: Option<String>) -> R
         "writes",
         "--daemon",  // Run in background
     ]);
+    cmd.args(mount_extra_args(&config_path, &remote_name)?);
 
-    if let Some(config_path_str) = config_path_opt {
-        let expanded_config_path = expand_tilde_path(&config_path_str)?;
-        cmd.arg("--config").arg(expanded_config_path);
-    }
+    // Serve rc from the mount daemon so its bandwidth limit can be changed later. The daemon
+    // logs to a file, that's where it tells which port it bound.
+    let credentials = rc::RcEndpoint::local()?;
+    let mount_key = rclone_config::remote_key(&config_path, &remote_name);
+    let log_file = rc::log_file(&format!("mount-{}", mount_key))?;
+    let _ = std::fs::remove_file(&log_file);
+    cmd.arg("--rc").arg("--log-file").arg(&log_file);
+    credentials.serve(&mut cmd);
+    cmd.arg("--config").arg(&config_path);
 
-    let output = cmd.output()
-        .map_err(|e| format!("Failed to execute rclone mount: {}", e))?;
+    let output = runner::output("mount", operation_id, cmd).await?;
+    let log = std::fs::read_to_string(&log_file).unwrap_or_default();
 
     if output.status.success() {
+        match log.lines().find_map(rc::serving_url) {
+            Some(url) => rc::register(&format!("mount:{}", mount_key), credentials.with_url(url), vec![format!("{}:", remote_name)]),
+            None => eprintln!("Mount of {} did not log its rc address, its bandwidth limit can't be changed", remote_name),
+        }
         Ok(CommandResult {
             success: true,
             message: format!("Successfully mounted {} at {}", remote_name, mount_point),
+            details: None,
         })
     } else {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Mount failed: {}", stderr))
+        Err(format!("Mount failed: {}", if stderr.trim().is_empty() { log.trim() } else { stderr.trim() }))
     }
 }
 
+// Filter and bandwidth flags configured for mounting a remote
+fn mount_extra_args(config_path: &Path, remote_name: &str) -> Result<Vec<String>, String> {
+    let rules = filters::mount_filters(config_path, remote_name)?;
+    let mut args = filters::write_args(&filters::mount_filter_name(config_path, remote_name), &rules)?;
+    args.extend(bandwidth::load()?.args(&[&format!("{}:", remote_name)]));
+    Ok(args)
+}
+
 // Unmount a remote
 #[tauri::command]
-async fn unmount_remote(remote_name: String, _config_path_opt: Option<String>) -> Result<CommandResult, String> {
-    let mount_point = get_mount_dir(&remote_name);
+async fn unmount_remote(remote_name: String, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<CommandResult, String> {
+    // Finds the profile's mount directory and the daemon serving it, unmounting doesn't read the config
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    let mount_point = get_mount_dir(&config_path, &remote_name);
 
-    if !is_mounted(&mount_point) {
+    if !is_mounted(&mount_point).await {
         return Ok(CommandResult {
             success: true,
             message: format!("{} is not mounted", remote_name),
+            details: None,
         });
     }
 
+    // Mounts of the rcd daemon are unmounted through it, anything else falls through to fusermount
+    if let Some(endpoint) = rcd::active_for(&config_path) {
+        if rcd::unmount(&endpoint, &mount_point, operation_id.clone()).await.is_ok() {
+            return Ok(CommandResult {
+                success: true,
+                message: format!("Successfully unmounted {}", remote_name),
+                details: None,
+            });
+        }
+    }
+
+    // A busy FUSE mount can block unmounting, so both attempts share one cancellable operation
+    let operation = runner::Operation::start("unmount", operation_id)?;
+
     // Try fusermount first (Linux) - this doesn't need config file
-    let output = Command::new("fusermount")
-        .args(&["-u", &mount_point])
-        .output();
+    let mut cmd = tokio::process::Command::new("fusermount");
+    cmd.args(["-u", &mount_point]);
+    let output = operation.output(cmd).await;
 
     match output {
+        Err(runner::RunError::Cancelled(id)) => Err(format!("Operation {} was cancelled", id)),
         Ok(output) if output.status.success() => {
+            rc::unregister(&format!("mount:{}", rclone_config::remote_key(&config_path, &remote_name)));
             Ok(CommandResult {
                 success: true,
                 message: format!("Successfully unmounted {}", remote_name),
+                details: None,
             })
         }
         _ => {
             // Try umount as fallback - this also doesn't need config file
-            let output = Command::new("umount")
-                .arg(&mount_point)
-                .output()
-                .map_err(|e| format!("Failed to execute unmount command: {}", e))?;
+            let mut cmd = tokio::process::Command::new("umount");
+            cmd.arg(&mount_point);
+            let output = operation.output(cmd).await?;
 
             if output.status.success() {
+                rc::unregister(&format!("mount:{}", rclone_config::remote_key(&config_path, &remote_name)));
                 Ok(CommandResult {
                     success: true,
                     message: format!("Successfully unmounted {}", remote_name),
+                    details: None,
                 })
             } else {
                 let stderr = String::from_utf8_lossy(&output.stderr);
@@ -237,22 +270,18 @@ async fn unmount_remote(remote_name: String, _config_path_opt: Option<String>) -
 
 // Test connection to a remote
 #[tauri::command]
-async fn test_connection(remote_name: String, config_path_opt: Option<String>) -> Result<CommandResult, String> {
-    let mut cmd = Command::new("rclone");
-    cmd.arg("lsf").arg(&format!("{}:", remote_name));
+async fn test_connection(remote_name: String, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<CommandResult, String> {
+    let mut cmd = tokio::process::Command::new("rclone");
+    cmd.arg("lsf").arg(format!("{}:", remote_name));
+    cmd.arg("--config").arg(rclone_config::resolve_config_path(config_path_opt)?);
 
-    if let Some(config_path_str) = config_path_opt {
-        let expanded_config_path = expand_tilde_path(&config_path_str)?;
-        cmd.arg("--config").arg(expanded_config_path);
-    }
-
-    let output = cmd.output()
-        .map_err(|e| format!("Failed to execute rclone test: {}", e))?;
+    let output = runner::output("test", operation_id, cmd).await?;
 
     if output.status.success() {
         Ok(CommandResult {
             success: true,
             message: format!("Connection to {} successful", remote_name),
+            details: None,
         })
     } else {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -260,6 +289,459 @@ async fn test_connection(remote_name: String, config_path_opt: Option<String>) -
     }
 }
 
+// Get total/used/free/trashed space of a remote via `rclone about`.
+// Results are cached for `max_age_secs` (5 minutes by default), 0 forces a refresh.
+#[tauri::command]
+async fn get_remote_usage(remote_name: String, config_path_opt: Option<String>, max_age_secs: Option<u64>, operation_id: Option<String>) -> Result<RemoteUsage, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    let ttl = std::time::Duration::from_secs(max_age_secs.unwrap_or(usage::DEFAULT_USAGE_TTL_SECS));
+    usage::remote_usage(&remote_name, &config_path, ttl, operation_id).await
+}
+
+// List a directory of a remote without mounting it, one page at a time.
+// Later pages reuse the listing fetched for the first one unless `refresh` is set.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn list_remote_dir(
+    remote_name: String,
+    path: Option<String>,
+    config_path_opt: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    with_hashes: Option<bool>,
+    refresh: Option<bool>,
+    operation_id: Option<String>,
+) -> Result<DirListing, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    browser::list_dir(
+        &remote_name,
+        &path.unwrap_or_default(),
+        &config_path,
+        offset.unwrap_or(0),
+        limit.unwrap_or(browser::DEFAULT_PAGE_SIZE),
+        with_hashes.unwrap_or(false),
+        refresh.unwrap_or(false),
+        operation_id,
+    )
+    .await
+}
+
+// Copy a file between remotes or local paths ("remote:dir/file", "/home/me/file").
+// Overwriting an existing destination needs `confirm`, otherwise the result asks for it.
+#[tauri::command]
+async fn copy_path(source: String, destination: String, confirm: Option<bool>, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<CommandResult, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    file_ops::run(FileOp::Copy { source, destination }, confirm.unwrap_or(false), &config_path, operation_id).await
+}
+
+// Move a file between remotes or local paths, overwriting needs `confirm`
+#[tauri::command]
+async fn move_path(source: String, destination: String, confirm: Option<bool>, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<CommandResult, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    file_ops::run(FileOp::Move { source, destination }, confirm.unwrap_or(false), &config_path, operation_id).await
+}
+
+// Rename a file or directory within its directory
+#[tauri::command]
+async fn rename_path(path: String, new_name: String, confirm: Option<bool>, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<CommandResult, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    let destination = file_ops::renamed_path(&path, &new_name)?;
+    file_ops::run(FileOp::Move { source: path, destination }, confirm.unwrap_or(false), &config_path, operation_id).await
+}
+
+// Delete a single file, only with `confirm`
+#[tauri::command]
+async fn delete_file(path: String, confirm: Option<bool>, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<CommandResult, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    file_ops::run(FileOp::Delete { path }, confirm.unwrap_or(false), &config_path, operation_id).await
+}
+
+#[tauri::command]
+async fn make_dir(path: String, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<CommandResult, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    file_ops::run(FileOp::Mkdir { path }, true, &config_path, operation_id).await
+}
+
+// Remove a directory. Empty directories are removed right away, with `recursive` the
+// directory and everything in it is purged, which needs `confirm`.
+#[tauri::command]
+async fn remove_dir(path: String, recursive: Option<bool>, confirm: Option<bool>, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<CommandResult, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    let op = if recursive.unwrap_or(false) {
+        FileOp::Purge { path }
+    } else {
+        FileOp::Rmdir { path }
+    };
+    file_ops::run(op, confirm.unwrap_or(false), &config_path, operation_id).await
+}
+
+// Settings kept in ~/.config/de_rclone/settings.json
+#[tauri::command]
+async fn get_settings() -> Result<Settings, String> {
+    settings::load()
+}
+
+// Replace the settings and apply timeouts and bandwidth limits right away. The backend is
+// left as it is, it changes through set_backend and attach_rcd.
+#[tauri::command]
+async fn update_settings(settings: Settings) -> Result<Settings, String> {
+    let updated = settings::modify(|current| {
+        *current = Settings {
+            version: current.version,
+            backend: current.backend.clone(),
+            ..settings
+        };
+        Ok(current.clone())
+    })?;
+
+    runner::set_timeouts(updated.timeouts.clone());
+    for limit in bandwidth::apply_runtime(&updated.bandwidth).await {
+        if let Some(e) = limit.error {
+            eprintln!("Failed to apply bandwidth limit to {}: {}", limit.process, e);
+        }
+    }
+    Ok(updated)
+}
+
+// Named rclone configs, the active one is used whenever no config path is given
+#[tauri::command]
+async fn list_profiles() -> Result<ProfileList, String> {
+    profiles::list()
+}
+
+#[tauri::command]
+async fn save_profile(profile: Profile) -> Result<Profile, String> {
+    profiles::save(profile)
+}
+
+#[tauri::command]
+async fn delete_profile(name: String) -> Result<CommandResult, String> {
+    profiles::delete(&name)?;
+    Ok(CommandResult {
+        success: true,
+        message: format!("Deleted profile '{}'", name),
+        details: None,
+    })
+}
+
+#[tauri::command]
+async fn switch_profile(name: String) -> Result<Profile, String> {
+    profiles::switch(&name)
+}
+
+#[derive(Serialize)]
+struct ProfileRemotes {
+    profile: String,
+    config_path: String,
+    remotes: Vec<Remote>,
+    // Set when the profile's config could not be read, the other profiles are still listed
+    error: Option<String>,
+}
+
+// Remotes of all profiles side by side
+#[tauri::command]
+async fn get_all_remotes(include_usage: Option<bool>) -> Result<Vec<ProfileRemotes>, String> {
+    let mut all = Vec::new();
+    for profile in profiles::list()?.profiles {
+        let (remotes, error) = match get_remotes(Some(profile.config_path.clone()), include_usage).await {
+            Ok(remotes) => (remotes, None),
+            Err(e) => (Vec::new(), Some(e)),
+        };
+        all.push(ProfileRemotes {
+            profile: profile.name,
+            config_path: profile.config_path,
+            remotes,
+            error,
+        });
+    }
+    Ok(all)
+}
+
+// Switch between running rclone per operation ("cli") and one `rclone rcd` daemon ("rcd").
+// Leaving rcd unmounts the daemon's mounts, so that needs `force` while it has any.
+#[tauri::command]
+async fn set_backend(backend: BackendKind, config_path_opt: Option<String>, force: Option<bool>) -> Result<BackendStatus, String> {
+    match backend {
+        BackendKind::Rcd => {
+            let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+            rcd::start(&config_path).await
+        }
+        BackendKind::Cli => {
+            rcd::stop(force.unwrap_or(false)).await?;
+            rcd::status().await
+        }
+        BackendKind::Attached => Err("Use attach_rcd to connect to a running rclone rcd".to_string()),
+    }
+}
+
+// Use an `rclone rcd` that already runs elsewhere, e.g. under systemd on a server. It is
+// remembered and attached to again on the next start until switching back to cli.
+#[tauri::command]
+async fn attach_rcd(url: String, user: Option<String>, pass: Option<String>) -> Result<BackendStatus, String> {
+    rcd::attach(&url, &user.unwrap_or_default(), &pass.unwrap_or_default()).await
+}
+
+fn rcd_endpoint() -> Result<rc::RcEndpoint, String> {
+    rcd::active().ok_or_else(|| "Not using rclone rcd".to_string())
+}
+
+// Jobs of the rcd daemon, also those started by other clients
+#[tauri::command]
+async fn list_rcd_jobs() -> Result<Vec<RcJob>, String> {
+    rcd::list_jobs(&rcd_endpoint()?).await
+}
+
+#[tauri::command]
+async fn stop_rcd_job(job_id: i64) -> Result<CommandResult, String> {
+    rcd::stop_job(&rcd_endpoint()?, job_id).await?;
+    Ok(CommandResult {
+        success: true,
+        message: format!("Stopped rcd job {}", job_id),
+        details: None,
+    })
+}
+
+// Remotes configured on the rcd daemon, which for an attached one differ from ours
+#[tauri::command]
+async fn list_rcd_remotes() -> Result<Vec<String>, String> {
+    rcd::list_remotes(&rcd_endpoint()?).await
+}
+
+// Mount a remote of the rcd daemon at a path on the machine it runs on
+#[tauri::command]
+async fn rcd_mount(remote_name: String, mount_point: String, operation_id: Option<String>) -> Result<CommandResult, String> {
+    rcd::mount(&rcd_endpoint()?, rcd::config_path().as_deref(), &remote_name, &mount_point, operation_id).await?;
+    Ok(CommandResult {
+        success: true,
+        message: format!("Successfully mounted {} at {} via rclone rcd", remote_name, mount_point),
+        details: None,
+    })
+}
+
+#[tauri::command]
+async fn rcd_unmount(mount_point: String, operation_id: Option<String>) -> Result<CommandResult, String> {
+    rcd::unmount(&rcd_endpoint()?, &mount_point, operation_id).await?;
+    Ok(CommandResult {
+        success: true,
+        message: format!("Successfully unmounted {}", mount_point),
+        details: None,
+    })
+}
+
+#[tauri::command]
+async fn get_backend_status() -> Result<BackendStatus, String> {
+    rcd::status().await
+}
+
+// Mounts of the rcd daemon plus rclone FUSE mounts started by the CLI
+#[tauri::command]
+async fn list_mounts() -> Result<Vec<MountInfo>, String> {
+    let mut mounts = match rcd::active() {
+        Some(endpoint) => rcd::list_mounts(&endpoint).await?,
+        None => Vec::new(),
+    };
+    for mount in rcd::system_mounts() {
+        if !mounts.iter().any(|listed| listed.mount_point == mount.mount_point) {
+            mounts.push(mount);
+        }
+    }
+    Ok(mounts)
+}
+
+// Global and per-remote bandwidth limits for mounts and jobs
+#[tauri::command]
+async fn get_bandwidth_settings() -> Result<BandwidthSettings, String> {
+    bandwidth::load()
+}
+
+// Save bandwidth limits and apply them right away to running mounts and jobs via rc core/bwlimit
+#[tauri::command]
+async fn set_bandwidth_settings(settings: BandwidthSettings) -> Result<CommandResult, String> {
+    bandwidth::save(&settings)?;
+    let applied = bandwidth::apply_runtime(&settings).await;
+    let failed = applied.iter().filter(|limit| limit.error.is_some()).count();
+
+    Ok(CommandResult {
+        success: failed == 0,
+        message: if failed == 0 {
+            format!("Saved bandwidth limits, applied to {} running processes", applied.len())
+        } else {
+            format!("Saved bandwidth limits, {} of {} running processes could not be updated", failed, applied.len())
+        },
+        details: Some(serde_json::json!({ "applied": applied })),
+    })
+}
+
+// Filter rules applied when mounting a remote, also for auto-mounts added afterwards
+#[tauri::command]
+async fn get_mount_filters(remote_name: String, config_path_opt: Option<String>) -> Result<FilterRules, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    filters::mount_filters(&config_path, &remote_name)
+}
+
+#[tauri::command]
+async fn set_mount_filters(remote_name: String, filters: FilterRules, config_path_opt: Option<String>) -> Result<CommandResult, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    filters::set_mount_filters(&config_path, &remote_name, filters)?;
+    Ok(CommandResult {
+        success: true,
+        message: format!("Saved filters for {}, they apply the next time it is mounted", remote_name),
+        details: None,
+    })
+}
+
+// Check which files filter rules let through, against sample paths or a "remote:path" target
+#[tauri::command]
+async fn test_filters(filters: FilterRules, sample_paths: Option<Vec<String>>, target: Option<String>, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<FilterTest, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    filters::test(&filters, &sample_paths.unwrap_or_default(), target.as_deref(), &config_path, operation_id).await
+}
+
+// Saved sync/copy/move/bisync jobs, stored in ~/.config/de_rclone/jobs.json
+#[tauri::command]
+async fn list_jobs() -> Result<Vec<Job>, String> {
+    jobs::list()
+}
+
+#[tauri::command]
+async fn create_job(job: Job) -> Result<Job, String> {
+    jobs::create(job)
+}
+
+#[tauri::command]
+async fn update_job(job: Job) -> Result<Job, String> {
+    jobs::update(job)
+}
+
+// Deleting a job also removes its systemd timer, if it was exported
+#[tauri::command]
+async fn delete_job(job_id: String) -> Result<CommandResult, String> {
+    let job = jobs::find(&job_id)?;
+    scheduler::remove_timer(&job).await?;
+    jobs::delete(&job_id)?;
+    history::delete_job_runs(&job_id)?;
+    Ok(CommandResult {
+        success: true,
+        message: format!("Job {} deleted", job_id),
+        details: None,
+    })
+}
+
+// Past runs of a job, or of all jobs without `job_id`, newest first
+#[tauri::command]
+async fn list_job_runs(job_id: Option<String>, limit: Option<usize>) -> Result<Vec<JobRun>, String> {
+    history::list(job_id.as_deref(), limit.unwrap_or(history::DEFAULT_RUN_LIMIT))
+}
+
+#[tauri::command]
+async fn get_job_run(run_id: String) -> Result<JobRun, String> {
+    history::get(&run_id)
+}
+
+// Scheduled jobs with their next run while the app is open
+#[tauri::command]
+async fn list_scheduled_jobs() -> Result<Vec<ScheduledJob>, String> {
+    Ok(scheduler::scheduled_jobs())
+}
+
+// Export a job's schedule as a systemd user timer so it also runs while the app is closed
+#[tauri::command]
+async fn export_job_timer(job_id: String, config_path_opt: Option<String>) -> Result<CommandResult, String> {
+    let job = jobs::find(&job_id)?;
+    let (service_path, timer_path) = scheduler::export_timer(&job, config_path_opt).await?;
+    Ok(CommandResult {
+        success: true,
+        message: format!("Job '{}' is scheduled with systemd", job.name),
+        details: Some(serde_json::json!({
+            "service_path": service_path,
+            "timer_path": timer_path,
+        })),
+    })
+}
+
+#[tauri::command]
+async fn remove_job_timer(job_id: String) -> Result<CommandResult, String> {
+    let job = jobs::find(&job_id)?;
+    scheduler::remove_timer(&job).await?;
+    Ok(CommandResult {
+        success: true,
+        message: format!("Removed the systemd timer of job '{}'", job.name),
+        details: None,
+    })
+}
+
+// What a job would change, from a dry run, so destructive syncs can be approved first
+#[tauri::command]
+async fn preview_job(job_id: String, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<JobPreview, String> {
+    let job = jobs::find(&job_id)?;
+    let config_path = job.resolve_config_path(config_path_opt)?;
+    jobs::preview(&job, &config_path, operation_id).await
+}
+
+// Run a job now and wait for it to finish, the rclone log is in the result details.
+// While it runs a "job-progress" event with the transfer stats is emitted every second.
+// Bisync jobs report conflicts in the details and ask for `resync` on their first run.
+#[tauri::command]
+async fn run_job(app_handle: tauri::AppHandle, job_id: String, config_path_opt: Option<String>, resync: Option<bool>, operation_id: Option<String>) -> Result<CommandResult, String> {
+    let job = jobs::find(&job_id)?;
+    let config_path = job.resolve_config_path(config_path_opt)?;
+    jobs::run(&job, &config_path, operation_id, "manual", resync.unwrap_or(false), |progress| {
+        if let Err(e) = app_handle.emit("job-progress", progress) {
+            eprintln!("Failed to emit job progress: {}", e);
+        }
+    })
+    .await
+}
+
+// Run timed checks against a remote and classify what fails.
+// The write/read/delete probe only runs when `write_probe` is set since it modifies the remote.
+#[tauri::command]
+async fn diagnose_remote(remote_name: String, config_path_opt: Option<String>, write_probe: Option<bool>, timeout_secs: Option<u64>, operation_id: Option<String>) -> Result<DiagnosticsReport, String> {
+    let operation = runner::Operation::start("diagnose", operation_id)?;
+    diagnostics::diagnose(&operation, &remote_name, config_path_opt, write_probe.unwrap_or(false), timeout_secs).await
+}
+
+// Cancel a running operation started with the given operation_id
+#[tauri::command]
+async fn cancel_operation(id: String) -> Result<CommandResult, String> {
+    if runner::cancel(&id) {
+        Ok(CommandResult {
+            success: true,
+            message: format!("Cancelled operation {}", id),
+            details: None,
+        })
+    } else {
+        Err(format!("No running operation {}", id))
+    }
+}
+
+// List operations that are still running
+#[tauri::command]
+async fn list_operations() -> Result<Vec<OperationInfo>, String> {
+    Ok(runner::list_operations())
+}
+
+// Get the time limits applied to each kind of operation
+#[tauri::command]
+async fn get_timeouts() -> Result<Timeouts, String> {
+    Ok(runner::timeouts())
+}
+
+// Change the time limits applied to each kind of operation, 0 disables a limit
+#[tauri::command]
+async fn set_timeouts(timeouts: Timeouts) -> Result<CommandResult, String> {
+    settings::modify(|settings| {
+        settings.timeouts = timeouts.clone();
+        Ok(())
+    })?;
+    runner::set_timeouts(timeouts);
+    Ok(CommandResult {
+        success: true,
+        message: "Timeouts updated".to_string(),
+        details: None,
+    })
+}
+
 // Open a folder in the system file manager
 #[tauri::command]
 async fn open_folder(path: String) -> Result<CommandResult, String> {
@@ -290,111 +772,146 @@ async fn open_folder(path: String) -> Result<CommandResult, String> {
     Ok(CommandResult {
         success: true,
         message: format!("Opened folder: {}", path),
+        details: None,
     })
 }
 
-// Helper function to get mount directory
-fn get_mount_dir(remote_name: &str) -> String {
-    let home_dir = std::env::var("HOME").expect("HOME environment variable not set");
-    format!("{}/mnt/{}", home_dir, remote_name)
+// Helper function to get mount directory, the mount_dir of the config's profile or ~/mnt
+fn get_mount_dir(config_path: &Path, remote_name: &str) -> String {
+    match profiles::for_config(config_path).and_then(|profile| profile.resolved_mount_dir().ok()) {
+        Some(mount_dir) => mount_dir.join(remote_name).to_string_lossy().to_string(),
+        None => {
+            let home_dir = std::env::var("HOME").expect("HOME environment variable not set");
+            format!("{}/mnt/{}", home_dir, remote_name)
+        }
+    }
 }
 
 // Helper function to check if directory is mounted
-fn is_mounted(mount_point: &str) -> bool {
-    Command::new("mountpoint")
-        .arg("-q")
-        .arg(mount_point)
-        .status()
-        .map(|status| status.success())
-        .unwrap_or_else(|_| {
-            // If mountpoint command is not available, check with Path::is_mounted (if available)
-            std::path::Path::new(mount_point).is_dir()
-        })
+async fn is_mounted(mount_point: &str) -> bool {
+    let mut cmd = tokio::process::Command::new("mountpoint");
+    cmd.arg("-q").arg(mount_point);
+
+    let result = match runner::Operation::start("mount_check", None) {
+        Ok(operation) => operation.output(cmd).await,
+        Err(_) => return in_proc_mounts(mount_point),
+    };
+    match result {
+        Ok(output) => output.status.success(),
+        // Only a stale FUSE mount makes mountpoint hang, so something is mounted there
+        Err(runner::RunError::TimedOut(..)) => true,
+        // mountpoint is not installed, /proc/mounts can be read without touching the mount
+        Err(_) => in_proc_mounts(mount_point),
+    }
+}
+
+fn in_proc_mounts(mount_point: &str) -> bool {
+    let mounts = fs::read_to_string("/proc/mounts").unwrap_or_default();
+    let mount_point = mount_point.trim_end_matches('/');
+    mounts
+        .lines()
+        // Spaces are escaped as \040
+        .filter_map(|line| line.split_whitespace().nth(1).map(|point| point.replace("\\040", " ")))
+        .any(|point| point == mount_point)
+}
+
+// The crontab line prefix that auto-mounts a remote
+fn cron_mount_cmd(remote_name: &str) -> String {
+    format!("rclone mount --vfs-cache-mode writes {}:", remote_name)
+}
+
+// The user's crontab, empty when there is none yet
+async fn read_crontab() -> Result<String, String> {
+    let mut cmd = tokio::process::Command::new("crontab");
+    cmd.arg("-l");
+    let output = runner::output("crontab", None, cmd).await?;
+
+    // crontab -l fails when the user has no crontab
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Ok(String::new())
+    }
+}
+
+// Replace the user's crontab. It goes through a file only the user can read, crontab reads
+// that the same as stdin.
+async fn write_crontab(content: &str) -> Result<(), String> {
+    let path = std::env::temp_dir().join(runner::generate_id("de_rclone-crontab"));
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    let mut cmd = tokio::process::Command::new("crontab");
+    cmd.arg(&path);
+    let output = runner::output("crontab", None, cmd).await;
+    let _ = fs::remove_file(&path);
+    let output = output?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
 }
 
 // Add cron entry for a remote
 #[tauri::command]
 async fn add_to_cron(remote_name: String) -> Result<CommandResult, String> {
-    // Get current crontab
-    let output = Command::new("crontab")
-        .arg("-l")
-        .output();
-
-    let current_cron = match output {
-        Ok(output) if output.status.success() => {
-            String::from_utf8_lossy(&output.stdout).to_string()
-        }
-        Ok(_) => String::new(), // Empty crontab
-        Err(e) => return Err(format!("Failed to read crontab: {}", e)),
-    };
+    let current_cron = read_crontab().await?;
 
     // Check if entry already exists
-    let mount_cmd = format!("rclone mount --vfs-cache-mode writes {}:", remote_name);
+    let mount_cmd = cron_mount_cmd(&remote_name);
     if current_cron.contains(&mount_cmd) {
         return Ok(CommandResult {
             success: true,
             message: format!("Remote {} is already scheduled for auto-mount", remote_name),
+            details: None,
         });
     }
 
-    // Add new entry
-    let mount_point = get_mount_dir(&remote_name);
-    let new_entry = format!("@reboot {} {}\n", mount_cmd, mount_point);
+    // Add new entry, mounting with the active profile's config
+    let config_path = rclone_config::resolve_config_path(None)?;
+    let mount_point = get_mount_dir(&config_path, &remote_name);
+    let mut extra_args = mount_extra_args(&config_path, &remote_name)?;
+    extra_args.extend(["--config".to_string(), config_path.to_string_lossy().to_string()]);
+    let extra_args: String = extra_args
+        .iter()
+        // Quoted for the shell, cron turns a bare % into a newline
+        .map(|arg| format!(" '{}'", arg.replace('\'', "'\\''").replace('%', "\\%")))
+        .collect();
+    let new_entry = format!("@reboot {} {}{}\n", mount_cmd, mount_point, extra_args);
     let new_cron = format!("{}{}", current_cron, new_entry);
 
-    // Write to crontab
-    let mut child = Command::new("crontab")
-        .stdin(std::process::Stdio::piped())
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to spawn crontab: {}", e))?;
-
-    let stdin = child.stdin.as_mut().unwrap();
-    stdin.write_all(new_cron.as_bytes())
-        .map_err(|e| format!("Failed to write to crontab: {}", e))?;
+    write_crontab(&new_cron)
+        .await
+        .map_err(|e| format!("Failed to add to crontab: {}", e))?;
 
-    let output = child.wait_with_output()
-        .map_err(|e| format!("Failed to wait for crontab: {}", e))?;
-
-    if output.status.success() {
-        Ok(CommandResult {
-            success: true,
-            message: format!("Added {} to crontab for auto-mount", remote_name),
-        })
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Failed to add to crontab: {}", stderr))
-    }
+    Ok(CommandResult {
+        success: true,
+        message: format!("Added {} to crontab for auto-mount", remote_name),
+        details: None,
+    })
 }
 
 // Remove cron entry for a remote
 #[tauri::command]
 async fn remove_from_cron(remote_name: String) -> Result<CommandResult, String> {
-    // Get current crontab
-    let output = Command::new("crontab")
-        .arg("-l")
-        .output();
-
-    let current_cron = match output {
-        Ok(output) if output.status.success() => {
-            String::from_utf8_lossy(&output.stdout).to_string()
-        }
-        Ok(_) => return Ok(CommandResult {
-            success: true,
-            message: "No crontab entries found".to_string(),
-        }), // Empty crontab
-        Err(e) => return Err(format!("Failed to read crontab: {}", e)),
-    };
+    let current_cron = read_crontab().await?;
 
     if current_cron.trim().is_empty() {
         return Ok(CommandResult {
             success: true,
             message: "No crontab entries found".to_string(),
+            details: None,
         });
     }
 
-    let mount_cmd = format!("rclone mount --vfs-cache-mode writes {}:", remote_name);
+    let mount_cmd = cron_mount_cmd(&remote_name);
 
     // Filter out entries with this remote
     let lines: Vec<&str> = current_cron.lines().collect();
@@ -403,64 +920,21 @@ async fn remove_from_cron(remote_name: String) -> Result<CommandResult, String>
         .collect();
     let new_cron = new_lines.join("\n") + "\n";
 
-    // Write to crontab
-    let mut child = Command::new("crontab")
-        .stdin(std::process::Stdio::piped())
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to spawn crontab: {}", e))?;
-
-    let stdin = child.stdin.as_mut().unwrap();
-    stdin.write_all(new_cron.as_bytes())
-        .map_err(|e| format!("Failed to write to crontab: {}", e))?;
+    write_crontab(&new_cron)
+        .await
+        .map_err(|e| format!("Failed to remove from crontab: {}", e))?;
 
-    let output = child.wait_with_output()
-        .map_err(|e| format!("Failed to wait for crontab: {}", e))?;
-
-    if output.status.success() {
-        Ok(CommandResult {
-            success: true,
-            message: format!("Removed {} from crontab", remote_name),
-        })
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Failed to remove from crontab: {}", stderr))
-    }
+    Ok(CommandResult {
+        success: true,
+        message: format!("Removed {} from crontab", remote_name),
+        details: None,
+    })
 }
 
 // Check if remote is in crontab
 #[tauri::command]
 async fn is_remote_in_cron(remote_name: String) -> Result<bool, String> {
-    let output = Command::new("crontab")
-        .arg("-l")
-        .output();
-
-    match output {
-        Ok(output) if output.status.success() => {
-            let crontab_content = String::from_utf8_lossy(&output.stdout);
-            let mount_cmd = format!("rclone mount --vfs-cache-mode writes {}:", remote_name);
-            Ok(crontab_content.contains(&mount_cmd))
-        }
-        Ok(_) => Ok(false), // Empty crontab
-        Err(e) => Err(format!("Failed to read crontab: {}", e)),
-    }
-}
-
-// Helper function to check if remote is in crontab
-fn is_in_crontab(remote_name: &str) -> bool {
-    let output = Command::new("crontab")
-        .arg("-l")
-        .output();
-
-    match output {
-        Ok(output) if output.status.success() => {
-            let crontab_content = String::from_utf8_lossy(&output.stdout);
-            let mount_cmd = format!("rclone mount --vfs-cache-mode writes {}:", remote_name);
-            crontab_content.contains(&mount_cmd)
-        }
-        _ => false,
-    }
+    Ok(read_crontab().await?.contains(&cron_mount_cmd(&remote_name)))
 }
 
 // Helper function to expand tilde paths
@@ -479,261 +953,303 @@ fn expand_tilde_path(path_str: &str) -> Result<PathBuf, String> {
 // Check if rclone is installed
 #[tauri::command]
 async fn is_rclone_installed() -> Result<bool, String> {
-    let output = Command::new("rclone")
-        .arg("--version")
-        .output();
+    let mut cmd = tokio::process::Command::new("rclone");
+    cmd.arg("--version");
 
-    match output {
+    match runner::output("version", None, cmd).await {
         Ok(output) => Ok(output.status.success()),
-        Err(_) => Ok(false), // Command not found
+        Err(_) => Ok(false), // Command not found or hung
     }
 }
 
-// Get available plugins
+// Get the plugins together with the plugin files that were skipped because they could not be loaded
 #[tauri::command]
-async fn get_available_plugins() -> Result<Vec<Plugin>, String> {
-    // Try multiple locations for plugins to support both dev and prod modes
-    let mut plugins_dir = std::path::PathBuf::new();
+async fn get_available_plugins(config_path_opt: Option<String>) -> Result<PluginRegistry, String> {
+    let mut registry = PluginRegistry::load();
 
-    // First, try relative to executable (for AppImage/prod)
-    if let Ok(exe_dir) = std::env::current_exe() {
-        if let Some(parent) = exe_dir.parent() {
-            let exe_plugins_dir = parent.join("plugins");
-            if exe_plugins_dir.exists() {
-                plugins_dir = exe_plugins_dir;
-            }
-        }
+    // A missing config just means there is nothing to reference yet
+    if let Ok(sections) = rclone_config::resolve_config_path(config_path_opt)
+        .and_then(|path| rclone_config::read_sections(&path))
+    {
+        let remote_names: Vec<String> = sections
+            .into_iter()
+            .filter(|s| !s.remote_type().is_empty())
+            .map(|s| s.name)
+            .collect();
+        registry.fill_remote_choices(&remote_names);
     }
 
-    // If not found relative to executable, try relative to current dir (for dev)
-    if !plugins_dir.exists() {
-        let current_dir_plugins = std::env::current_dir()
-            .map_err(|e| format!("Failed to get current directory: {}", e))?
-            .join("..")  // Go up to project root
-            .join("plugins");
-        if current_dir_plugins.exists() {
-            plugins_dir = current_dir_plugins;
-        } else {
-            // Try current directory directly
-            let current_plugins = std::env::current_dir()
-                .map_err(|e| format!("Failed to get current directory: {}", e))?
-                .join("plugins");
-            if current_plugins.exists() {
-                plugins_dir = current_plugins;
-            }
-        }
-    }
+    Ok(registry)
+}
 
-    if !plugins_dir.exists() {
-        return Ok(Vec::new());
-    }
+// Get the directories searched for plugins, highest precedence first
+#[tauri::command]
+async fn get_plugin_dirs() -> Result<Vec<PluginDir>, String> {
+    Ok(PluginRegistry::search_dirs())
+}
 
-    let mut plugins = Vec::new();
-    for entry in std::fs::read_dir(&plugins_dir)
-        .map_err(|e| format!("Failed to read plugins directory: {}", e))?
-    {
-        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-        let path = entry.path();
+// Get the JSON Schema describing plugin config.json files
+#[tauri::command]
+async fn get_plugin_schema() -> Result<serde_json::Value, String> {
+    Ok(plugins::plugin_json_schema())
+}
+
+// Write the plugin JSON Schema to a file so editors can use it for completion
+#[tauri::command]
+async fn export_plugin_schema(path: String) -> Result<CommandResult, String> {
+    let schema_path = expand_tilde_path(&path)?;
+    let schema = serde_json::to_string_pretty(&plugins::plugin_json_schema())
+        .map_err(|e| format!("Failed to serialize plugin schema: {}", e))?;
 
-        if path.is_dir() {
-            let config_path = path.join("config.json");
-            if config_path.exists() {
-                let config_content = std::fs::read_to_string(&config_path)
-                    .map_err(|e| format!("Failed to read plugin config: {}", e))?;
+    fs::write(&schema_path, schema)
+        .map_err(|e| format!("Failed to write plugin schema to {:?}: {}", schema_path, e))?;
 
-                let plugin: Plugin = serde_json::from_str(&config_content)
-                    .map_err(|e| format!("Failed to parse plugin config: {}", e))?;
+    Ok(CommandResult {
+        success: true,
+        message: format!("Plugin schema written to {:?}", schema_path),
+        details: None,
+    })
+}
 
-                plugins.push(plugin);
-            }
-        }
+// Validate a plugin config.json against the plugin format and the installed rclone
+#[tauri::command]
+async fn validate_plugin(path: String) -> Result<PluginValidation, String> {
+    let plugin_path = expand_tilde_path(&path)?;
+    if !plugin_path.exists() {
+        return Err(format!("Plugin file not found at {:?}", plugin_path));
+    }
+    Ok(plugins::validate_plugin_file(&plugin_path).await)
+}
+
+// Run the OAuth flow for a backend and return the token, e.g. to re-authorize an existing remote
+#[tauri::command]
+async fn authorize_backend(backend: String, client_id: Option<String>, client_secret: Option<String>, operation_id: Option<String>) -> Result<String, String> {
+    oauth::authorize(&backend, client_id.as_ref(), client_secret.as_ref(), operation_id).await
+}
+
+// Check one value of a plugin form against its field definition
+fn validate_plugin_field(field: &PluginField, value: Option<&String>) -> Result<(), String> {
+    // OAuth tokens are obtained in add_remote_with_plugin when missing
+    if field.required && field.field_type != "oauth_token" && value.is_none() {
+        return Err(format!("Required field '{}' is missing", field.name));
     }
 
-    Ok(plugins)
+    if let Some(value) = value {
+        // Basic validation based on field type
+        match field.field_type.as_str() {
+            "number" if value.parse::<f64>().is_err() => {
+                return Err(format!("Field '{}' must be a number", field.name));
+            },
+            "checkbox" if value != "true" && value != "false" => {
+                return Err(format!("Field '{}' must be true or false", field.name));
+            },
+            "oauth_token" if !value.trim().is_empty() => {
+                oauth::normalize_token(value)
+                    .map_err(|e| format!("Field '{}': {}", field.name, e))?;
+            },
+            _ => {} // Other types don't need specific validation here
+        }
+    }
+    Ok(())
 }
 
 // Add a new remote using a plugin
 #[tauri::command]
-async fn add_remote_with_plugin(plugin_name: String, config: std::collections::HashMap<String, String>, config_path_opt: Option<String>) -> Result<CommandResult, String> {
-    // Find plugin configuration in multiple possible locations
-    let mut plugin_config_path = std::path::PathBuf::new();
-    let mut found = false;
-
-    // First, try relative to executable (for AppImage/prod)
-    if let Ok(exe_dir) = std::env::current_exe() {
-        if let Some(parent) = exe_dir.parent() {
-            let exe_plugin_path = parent.join("plugins").join(&plugin_name).join("config.json");
-            if exe_plugin_path.exists() {
-                plugin_config_path = exe_plugin_path;
-                found = true;
+async fn add_remote_with_plugin(plugin_name: String, mut config: std::collections::HashMap<String, String>, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<CommandResult, String> {
+    let registry = PluginRegistry::load();
+    let plugin = match registry.find(&plugin_name) {
+        Some(plugin) => plugin,
+        None => {
+            if let Some(e) = registry.find_error(&plugin_name) {
+                return Err(format!("Plugin {} could not be loaded: {}", plugin_name, e.message));
             }
+            return Err(format!("Plugin {} not found", plugin_name));
         }
+    };
+
+    // Validate the provided configuration against the plugin schema, basic and advanced fields alike
+    for field in plugin.basic_fields.iter().chain(plugin.advanced_fields.iter()) {
+        validate_plugin_field(field, config.get(&field.name))?;
     }
 
-    // If not found relative to executable, try relative to current dir (for dev)
-    if !found {
-        let current_dir_plugin = std::env::current_dir()
-            .map_err(|e| format!("Failed to get current directory: {}", e))?
-            .join("..")  // Go up to project root
-            .join("plugins")
-            .join(&plugin_name)
-            .join("config.json");
-        if current_dir_plugin.exists() {
-            plugin_config_path = current_dir_plugin;
-            found = true;
-        } else {
-            // Try current directory directly
-            let current_plugin = std::env::current_dir()
-                .map_err(|e| format!("Failed to get current directory: {}", e))?
-                .join("plugins")
-                .join(&plugin_name)
-                .join("config.json");
-            if current_plugin.exists() {
-                plugin_config_path = current_plugin;
-                found = true;
-            }
+    // Token based backends - run the OAuth flow in the browser for any token the user didn't paste
+    for field in plugin.basic_fields.iter().chain(plugin.advanced_fields.iter()) {
+        if field.field_type != "oauth_token" {
+            continue;
         }
+
+        let token = match config.get(&field.name) {
+            Some(value) if !value.trim().is_empty() => oauth::normalize_token(value)?,
+            _ => oauth::authorize(&plugin_name, config.get("client_id"), config.get("client_secret"), operation_id.clone()).await?,
+        };
+        config.insert(field.name.clone(), token);
     }
 
-    if !found {
-        return Err(format!("Plugin {} not found", plugin_name));
+    // Prepare the configuration for saving - obscure passwords where needed
+    let mut processed_config = std::collections::HashMap::new();
+    for (key, value) in config {
+        if key == "pass" && !value.is_empty() { // For now, just obscure the password field - this could be extended for other sensitive fields
+            processed_config.insert(key, obscure_password(&value).await?);
+        } else {
+            processed_config.insert(key, value); // Keep empty passwords as-is
+        }
     }
 
-    let config_content = std::fs::read_to_string(&plugin_config_path)
-        .map_err(|e| format!("Failed to read plugin config: {}", e))?;
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
 
-    let plugin: Plugin = serde_json::from_str(&config_content)
-        .map_err(|e| format!("Failed to parse plugin config: {}", e))?;
+    // Generate the new remote configuration
+    let remote_name = processed_config.remove("remote_name").ok_or("remote_name is required")?;
+    let options: Vec<(String, String)> = processed_config.into_iter().collect();
 
-    // Validate the provided configuration against the plugin schema - check basic fields
-    for field in &plugin.basic_fields {
-        if field.required && !config.contains_key(&field.name) {
-            return Err(format!("Required field '{}' is missing", field.name));
-        }
+    create_remote(&config_path, &remote_name, &plugin_name, &options).await?;
 
-        if let Some(value) = config.get(&field.name) {
-            // Basic validation based on field type
-            match field.field_type.as_str() {
-                "number" => {
-                    if value.parse::<f64>().is_err() {
-                        return Err(format!("Field '{}' must be a number", field.name));
-                    }
-                },
-                "checkbox" => {
-                    if value != "true" && value != "false" {
-                        return Err(format!("Field '{}' must be true or false", field.name));
-                    }
-                },
-                _ => {} // Other types don't need specific validation here
-            }
-        }
+    Ok(CommandResult {
+        success: true,
+        message: format!("Successfully added remote '{}'", remote_name),
+        details: None,
+    })
+}
+
+// Remotes from the daemon serving this config, or from the config file directly
+async fn config_sections(config_path: &Path) -> Result<Vec<rclone_config::ConfigSection>, String> {
+    match rcd::active_for(config_path) {
+        Some(endpoint) => rcd::config_sections(&endpoint).await,
+        None => rclone_config::read_sections(config_path),
     }
+}
 
-    // Validate advanced fields as well
-    for field in &plugin.advanced_fields {
-        if field.required && !config.contains_key(&field.name) {
-            return Err(format!("Required field '{}' is missing", field.name));
-        }
+// Add a remote through the daemon serving this config, or to the config file directly
+async fn create_remote(config_path: &Path, name: &str, remote_type: &str, options: &[(String, String)]) -> Result<(), String> {
+    match rcd::active_for(config_path) {
+        Some(endpoint) => rcd::create_remote(&endpoint, name, remote_type, options).await,
+        None => rclone_config::append_section(config_path, name, remote_type, options),
+    }
+}
 
-        if let Some(value) = config.get(&field.name) {
-            // Basic validation based on field type
-            match field.field_type.as_str() {
-                "number" => {
-                    if value.parse::<f64>().is_err() {
-                        return Err(format!("Field '{}' must be a number", field.name));
-                    }
-                },
-                "checkbox" => {
-                    if value != "true" && value != "false" {
-                        return Err(format!("Field '{}' must be true or false", field.name));
-                    }
-                },
-                _ => {} // Other types don't need specific validation here
-            }
-        }
+// Encrypt a password the way rclone expects it in rclone.conf
+async fn obscure_password(password: &str) -> Result<String, String> {
+    let mut cmd = tokio::process::Command::new("rclone");
+    cmd.arg("obscure").arg(password);
+    let output = runner::output("obscure", None, cmd).await?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to obscure password: {}", stderr));
     }
 
-    // Prepare the configuration for saving - obscure passwords where needed
-    let mut processed_config = std::collections::HashMap::new();
-    for (key, value) in config {
-        if key == "pass" { // For now, just obscure the password field - this could be extended for other sensitive fields
-            if !value.is_empty() {
-                // Call rclone obscure to encrypt the password
-                let output = std::process::Command::new("rclone")
-                    .arg("obscure")
-                    .arg(&value)
-                    .output()
-                    .map_err(|e| format!("Failed to run rclone obscure: {}", e))?;
-
-                if !output.status.success() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    return Err(format!("Failed to obscure password: {}", stderr));
-                }
+    Ok(String::from_utf8(output.stdout)
+        .map_err(|e| format!("Invalid UTF-8 in rclone output: {}", e))?
+        .trim()
+        .to_string())
+}
 
-                let obscured_password = String::from_utf8(output.stdout)
-                    .map_err(|e| format!("Invalid UTF-8 in rclone output: {}", e))?
-                    .trim()
-                    .to_string();
+// Create a crypt remote on top of an existing remote
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn add_crypt_remote(
+    remote_name: String,
+    base_remote: String,
+    base_path: String,
+    filename_encryption: String,
+    directory_name_encryption: bool,
+    password: String,
+    password2: Option<String>,
+    config_path_opt: Option<String>,
+) -> Result<CommandResult, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    // The same source create_remote writes to, the file may lag behind a daemon
+    let sections = config_sections(&config_path).await?;
+
+    if !sections.iter().any(|s| s.name == base_remote && !s.remote_type().is_empty()) {
+        return Err(format!("Base remote '{}' not found in config", base_remote));
+    }
+    if base_remote == remote_name {
+        return Err("A crypt remote can't wrap itself".to_string());
+    }
 
-                processed_config.insert(key, obscured_password);
-            } else {
-                processed_config.insert(key, value); // Keep empty passwords as-is
-            }
-        } else {
-            processed_config.insert(key, value);
-        }
+    if !["standard", "obfuscate", "off"].contains(&filename_encryption.as_str()) {
+        return Err(format!("Filename encryption must be standard, obfuscate or off, got '{}'", filename_encryption));
+    }
+    if password.is_empty() {
+        return Err("Password is required".to_string());
     }
 
-    // Use the provided config path or default to ~/.config/rclone/rclone.conf
-    let config_path = if let Some(path_str) = config_path_opt {
-        expand_tilde_path(&path_str)?
-    } else {
-        // Use the default path
-        let home_dir = std::env::var("HOME").map_err(|e| format!("HOME not set: {}", e))?;
-        std::path::PathBuf::from(&home_dir).join(".config").join("rclone").join("rclone.conf")
-    };
+    let target = format!("{}:{}", base_remote, base_path.trim_start_matches('/'));
 
-    // Create the parent directory if it doesn't exist
-    if let Some(parent) = config_path.parent() {
-        std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    let mut options = vec![
+        ("remote".to_string(), target.clone()),
+        ("filename_encryption".to_string(), filename_encryption),
+        ("directory_name_encryption".to_string(), directory_name_encryption.to_string()),
+        ("password".to_string(), obscure_password(&password).await?),
+    ];
+
+    // The salt password is optional but strongly recommended
+    if let Some(salt) = password2.filter(|p| !p.is_empty()) {
+        options.push(("password2".to_string(), obscure_password(&salt).await?));
     }
 
-    // Read the existing config
-    let mut config_content = if config_path.exists() {
-        std::fs::read_to_string(&config_path)
-            .map_err(|e| format!("Failed to read rclone config: {}", e))?
-    } else {
-        String::new()
-    };
+    create_remote(&config_path, &remote_name, "crypt", &options).await?;
 
-    // Generate the new remote configuration
-    let remote_name = processed_config.get("remote_name").ok_or("remote_name is required")?;
-    let mut remote_config = format!("\n[{}]\ntype = {}\n", remote_name, plugin_name);
-
-    for (key, value) in &processed_config {
-        if key != "remote_name" {  // Skip the remote name field as it's used for the section name
-            // For checkbox fields in rclone config, we need to handle boolean values specially
-            if value == "true" {
-                remote_config.push_str(&format!("{} = true\n", key));
-            } else if value == "false" {
-                remote_config.push_str(&format!("{} = false\n", key));
-            } else {
-                remote_config.push_str(&format!("{} = {}\n", key, value));
-            }
-        }
-    }
+    Ok(CommandResult {
+        success: true,
+        message: format!("Successfully added crypt remote '{}' over {}", remote_name, target),
+        details: None,
+    })
+}
+
+// Create an alias/union/combine/chunker/compress/hasher remote on top of existing remotes.
+// `upstreams` are "remote:path" targets, "dir=remote:path" for combine.
+#[tauri::command]
+async fn add_wrapper_remote(
+    remote_name: String,
+    remote_type: String,
+    upstreams: Vec<String>,
+    options: Option<std::collections::HashMap<String, String>>,
+    config_path_opt: Option<String>,
+) -> Result<CommandResult, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    let sections = config_sections(&config_path).await?;
+
+    let remote_options = wrappers::build_options(&remote_name, &remote_type, &upstreams, &options.unwrap_or_default(), &sections)?;
+    create_remote(&config_path, &remote_name, &remote_type, &remote_options).await?;
+
+    Ok(CommandResult {
+        success: true,
+        message: format!("Successfully added {} remote '{}'", remote_type, remote_name),
+        details: None,
+    })
+}
 
-    // Append the new remote to the config
-    config_content.push_str(&remote_config);
+// List the backends the installed rclone can configure
+#[tauri::command]
+async fn list_rclone_backends() -> Result<Vec<Backend>, String> {
+    wizard::list_backends().await
+}
 
-    // Write the updated config back
-    std::fs::write(&config_path, config_content)
-        .map_err(|e| format!("Failed to write rclone config: {}", e))?;
+// Start adding a remote by letting rclone ask its own questions instead of using a plugin.
+// `params` holds answers already known up front, e.g. from a plugin form.
+#[tauri::command]
+async fn start_remote_config(remote_name: String, backend: String, params: Option<std::collections::HashMap<String, String>>, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<ConfigQuestion, String> {
+    if remote_name.trim().is_empty() {
+        return Err("remote_name is required".to_string());
+    }
+    wizard::start(&remote_name, &backend, &params.unwrap_or_default(), config_path_opt, operation_id).await
+}
 
+// Answer the question returned by start_remote_config or a previous continue_remote_config
+#[tauri::command]
+async fn continue_remote_config(remote_name: String, state: String, result: String, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<ConfigQuestion, String> {
+    wizard::answer(&remote_name, &state, &result, config_path_opt, operation_id).await
+}
+
+// Abandon a remote setup that was started with start_remote_config
+#[tauri::command]
+async fn abort_remote_config(remote_name: String, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<CommandResult, String> {
+    wizard::abort(&remote_name, config_path_opt, operation_id).await?;
     Ok(CommandResult {
         success: true,
-        message: format!("Successfully added remote '{}'", remote_name),
+        message: format!("Cancelled setup of remote '{}'", remote_name),
+        details: None,
     })
 }
 
@@ -753,151 +1269,123 @@ async fn open_file_dialog(app_handle: tauri::AppHandle) -> Result<Option<String>
 // Command to get remote config
 #[tauri::command]
 async fn get_remote_config(remote_name: String, config_path_opt: Option<String>) -> Result<std::collections::HashMap<String, String>, String> {
-    // Use the provided config path or default to ~/.config/rclone/rclone.conf
-    let config_path = if let Some(path_str) = config_path_opt {
-        expand_tilde_path(&path_str)?
-    } else {
-        // Use the default path
-        let home_dir = std::env::var("HOME").map_err(|e| format!("HOME not set: {}", e))?;
-        std::path::PathBuf::from(&home_dir).join(".config").join("rclone").join("rclone.conf")
-    };
-
-    if !config_path.exists() {
-        return Err(format!("rclone.conf not found at {:?}", config_path));
-    }
-
-    // Read the config file
-    let config_content = std::fs::read_to_string(&config_path)
-        .map_err(|e| format!("Failed to read config from {:?}: {}", config_path, e))?;
-
-    // Read all lines first to avoid iterator issues
-    let all_lines: Vec<&str> = config_content.lines().collect();
-    let mut result = std::collections::HashMap::new();
-    let mut in_target_section = false;
-    let mut i = 0;
-
-    while i < all_lines.len() {
-        let line = all_lines[i];
-        let line_trimmed = line.trim();
-
-        if line_trimmed.starts_with('[') && line_trimmed.ends_with(']') {
-            // This is a section header
-            let current_section = line_trimmed[1..line_trimmed.len()-1].to_string();
-            in_target_section = current_section == remote_name;
-
-            if in_target_section {
-                // We're now inside the target section, continue to read its options
-                i += 1;
-                continue;
-            } else if in_target_section {
-                // If we were in the target section and now moved to another section, we're done
-                break;
-            }
-        } else if in_target_section {
-            // Inside the target section, parse key=value pairs
-            if let Some(pos) = line_trimmed.find('=') {
-                let key = line_trimmed[..pos].trim();
-                let value = line_trimmed[pos + 1..].trim();
-
-                // Handle multiline values (like pem keys) if needed
-                if (key == "key_pem" || key == "pubkey") && value.starts_with("-----BEGIN ") {
-                    let mut full_value = value.to_string();
-                    i += 1;
-
-                    // Collect remaining lines until we find another section or end
-                    while i < all_lines.len() {
-                        let next_line = all_lines[i];
-                        if next_line.trim().starts_with('[') {
-                            // Hit next section, break out of multiline collection
-                            break;
-                        }
-
-                        if !next_line.trim().is_empty() {
-                            full_value.push('\n');
-                            full_value.push_str(next_line.trim());
-                        }
-                        i += 1;
-                    }
-
-                    // We've already incremented i, so continue the outer loop
-                    continue;
-                } else {
-                    result.insert(key.to_string(), value.to_string());
-                }
-            }
-        }
-
-        i += 1;
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    if let Some(endpoint) = rcd::active_for(&config_path) {
+        return rcd::remote_config(&endpoint, &remote_name).await;
     }
 
-    if !in_target_section && result.is_empty() {
-        return Err(format!("Remote '{}' not found in config", remote_name));
-    }
+    rclone_config::read_sections(&config_path)?
+        .into_iter()
+        .find(|section| section.name == remote_name)
+        .map(|section| section.options.into_iter().collect())
+        .ok_or_else(|| format!("Remote '{}' not found in config", remote_name))
+}
 
-    Ok(result)
+// Get how remotes wrap each other (crypt/alias/union/... pointing at other remotes)
+#[tauri::command]
+async fn get_remote_dependencies(config_path_opt: Option<String>) -> Result<RemoteGraph, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    let sections = rclone_config::read_sections(&config_path)?;
+    Ok(dependencies::build_graph(&sections))
 }
 
-// Command to delete a remote from the config
+// Command to delete a remote from the config.
+// `on_dependents` decides what happens to remotes that still wrap it:
+//   "refuse"  (default) - don't delete anything
+//   "cascade" - delete them as well, including remotes wrapping those
+//   "repoint" - point them at `repoint_to` instead
 #[tauri::command]
-async fn delete_remote(remote_name: String, config_path_opt: Option<String>) -> Result<CommandResult, String> {
-    // Use the provided config path or default to ~/.config/rclone/rclone.conf
-    let config_path = if let Some(path_str) = config_path_opt {
-        expand_tilde_path(&path_str)?
-    } else {
-        // Use the default path
-        let home_dir = std::env::var("HOME").map_err(|e| format!("HOME not set: {}", e))?;
-        std::path::PathBuf::from(&home_dir).join(".config").join("rclone").join("rclone.conf")
+async fn delete_remote(
+    remote_name: String,
+    config_path_opt: Option<String>,
+    on_dependents: Option<String>,
+    repoint_to: Option<String>,
+) -> Result<CommandResult, String> {
+    let config_path = rclone_config::resolve_config_path(config_path_opt)?;
+    let endpoint = rcd::active_for(&config_path);
+
+    let sections = match &endpoint {
+        Some(endpoint) => rcd::config_sections(endpoint).await?,
+        None => rclone_config::read_sections(&config_path)?,
     };
-
-    println!("Looking for config at path: {:?}", config_path); // Debug log
-
-    if !config_path.exists() {
-        return Err(format!("rclone.conf not found at {:?}", config_path));
+    if !sections.iter().any(|s| s.name == remote_name) {
+        return Err(format!("Remote '{}' not found in config", remote_name));
     }
 
-    // Read the config file
-    let config_content = std::fs::read_to_string(&config_path)
-        .map_err(|e| format!("Failed to read config from {:?}: {}", config_path, e))?;
-
-    // Parse the config to find the remote section to delete
-    let lines: Vec<&str> = config_content.split('\n').collect();
-    let mut new_config_lines = Vec::new();
-    let mut in_target_section = false;
-
-    for line in &lines {
-        let line_trimmed = line.trim();
+    // Remotes that wrap this one would stop working once it is gone
+    let dependents = rclone_config::dependents_of(&sections, &remote_name);
+
+    // What to delete, and which dependents to point where instead
+    let (removed, repoint, message) = match on_dependents.as_deref().unwrap_or("refuse") {
+        _ if dependents.is_empty() => (
+            vec![remote_name.clone()],
+            None,
+            format!("Successfully deleted remote '{}'", remote_name),
+        ),
+        "refuse" => {
+            return Err(format!(
+                "Remote '{}' is still used by {}. Delete them too or point them at another remote",
+                remote_name,
+                dependents.join(", ")
+            ));
+        }
+        "cascade" => {
+            let removed = dependencies::dependents_closure(&sections, &remote_name);
+            let message = format!("Successfully deleted remote '{}' and {}", remote_name, removed[1..].join(", "));
+            (removed, None, message)
+        }
+        "repoint" => {
+            let new_remote = repoint_to
+                .filter(|r| !r.is_empty())
+                .ok_or("repoint_to is required to re-point dependent remotes")?;
+            // Pointing at the remote itself or anything wrapping it, even indirectly, makes a cycle
+            if dependencies::dependents_closure(&sections, &remote_name).contains(&new_remote) {
+                return Err(format!("Can't re-point dependents of '{}' at '{}'", remote_name, new_remote));
+            }
+            if !sections.iter().any(|s| s.name == new_remote) {
+                return Err(format!("Remote '{}' not found in config", new_remote));
+            }
 
-        if line_trimmed.starts_with('[') && line_trimmed.ends_with(']') {
-            // This is a section header
-            let current_section = line_trimmed[1..line_trimmed.len()-1].to_string();
-            in_target_section = current_section == remote_name;
+            let message = format!(
+                "Successfully deleted remote '{}', {} now point at '{}'",
+                remote_name,
+                dependents.join(", "),
+                new_remote
+            );
+            (vec![remote_name.clone()], Some(new_remote), message)
+        }
+        other => return Err(format!("Unknown on_dependents option '{}', expected refuse, cascade or repoint", other)),
+    };
 
-            if in_target_section {
-                // Skip this section header (don't add to new config)
-                continue;
-            } else {
-                // Add other section headers
-                new_config_lines.push(line.as_ref());
+    match &endpoint {
+        Some(endpoint) => {
+            if let Some(new_remote) = &repoint {
+                for section in sections.iter().filter(|s| dependents.contains(&s.name)) {
+                    let options = rclone_config::repointed_options(section, &remote_name, new_remote);
+                    rcd::update_remote(endpoint, &section.name, &options).await?;
+                }
+            }
+            for name in &removed {
+                rcd::delete_remote(endpoint, name).await?;
             }
-        } else if in_target_section {
-            // Skip the content of the target section
-            continue;
-        } else {
-            // Add content from other sections
-            new_config_lines.push(line.as_ref());
         }
-    }
-
-    // Join the lines back together
-    let new_config_content = new_config_lines.join("\n");
+        None => {
+            let mut config_content = std::fs::read_to_string(&config_path)
+                .map_err(|e| format!("Failed to read config from {:?}: {}", config_path, e))?;
+            if let Some(new_remote) = &repoint {
+                config_content = rclone_config::repoint_sections(&config_content, &dependents, &remote_name, new_remote);
+            }
 
-    // Write the updated config back to the file
-    std::fs::write(&config_path, new_config_content)
-        .map_err(|e| format!("Failed to write updated config: {}", e))?;
+            // Write the updated config back to the file
+            std::fs::write(&config_path, rclone_config::remove_sections(&config_content, &removed))
+                .map_err(|e| format!("Failed to write updated config: {}", e))?;
+        }
+    }
 
     Ok(CommandResult {
         success: true,
-        message: format!("Successfully deleted remote '{}'", remote_name),
+        message,
+        details: None,
     })
 }
 
@@ -911,15 +1399,72 @@ fn main() {
             mount_remote,
             unmount_remote,
             test_connection,
+            diagnose_remote,
+            get_remote_usage,
+            list_remote_dir,
+            copy_path,
+            move_path,
+            rename_path,
+            delete_file,
+            make_dir,
+            remove_dir,
+            get_bandwidth_settings,
+            set_bandwidth_settings,
+            get_mount_filters,
+            set_mount_filters,
+            test_filters,
+            set_backend,
+            get_backend_status,
+            list_mounts,
+            get_settings,
+            update_settings,
+            list_profiles,
+            save_profile,
+            delete_profile,
+            switch_profile,
+            get_all_remotes,
+            attach_rcd,
+            list_rcd_jobs,
+            stop_rcd_job,
+            list_rcd_remotes,
+            rcd_mount,
+            rcd_unmount,
+            list_jobs,
+            create_job,
+            update_job,
+            delete_job,
+            preview_job,
+            run_job,
+            list_job_runs,
+            get_job_run,
+            list_scheduled_jobs,
+            export_job_timer,
+            remove_job_timer,
+            cancel_operation,
+            list_operations,
+            get_timeouts,
+            set_timeouts,
             open_folder,
             add_to_cron,
             remove_from_cron,
             is_remote_in_cron,
             is_rclone_installed,
             get_available_plugins,
+            get_plugin_dirs,
+            get_plugin_schema,
+            export_plugin_schema,
+            validate_plugin,
             add_remote_with_plugin,
+            authorize_backend,
+            add_crypt_remote,
+            add_wrapper_remote,
+            list_rclone_backends,
+            start_remote_config,
+            continue_remote_config,
+            abort_remote_config,
             open_file_dialog,
             delete_remote,
+            get_remote_dependencies,
             get_remote_config
         ])
         .setup(|app| {
@@ -931,6 +1476,13 @@ fn main() {
             } else {
                 eprintln!("Failed to get main window");
             }
+
+            scheduler::start(app.app_handle().clone());
+            match settings::load() {
+                Ok(settings) => runner::set_timeouts(settings.timeouts),
+                Err(e) => eprintln!("Failed to load settings: {}", e),
+            }
+            tauri::async_runtime::spawn(rcd::restore());
             Ok(())
         })
         .run(tauri::generate_context!())
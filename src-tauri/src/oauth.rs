@@ -0,0 +1,97 @@
+use tokio::process::Command;
+
+use crate::runner;
+
+// Markers rclone authorize prints around the token JSON
+const TOKEN_START: &str = "--->";
+const TOKEN_END: &str = "<---End paste";
+
+// Run `rclone authorize <backend>` which opens the provider's consent page in the browser,
+// waits for the redirect and prints the resulting token. Returns the token as single line JSON,
+// ready to be written as `token = ...` in rclone.conf.
+pub async fn authorize(backend: &str, client_id: Option<&String>, client_secret: Option<&String>, operation_id: Option<String>) -> Result<String, String> {
+    let mut cmd = Command::new("rclone");
+    cmd.arg("authorize").arg(backend);
+
+    // Custom OAuth apps are passed positionally, both or neither
+    if let (Some(id), Some(secret)) = (client_id, client_secret) {
+        if !id.is_empty() && !secret.is_empty() {
+            cmd.arg(id).arg(secret);
+        }
+    }
+
+    // Cancelling kills rclone, which also stops its local redirect listener
+    let output = runner::output("authorize", operation_id, cmd).await?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Authorization failed: {}", stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let token = parse_authorize_output(&stdout)
+        .ok_or_else(|| "rclone authorize finished without printing a token".to_string())?;
+
+    normalize_token(&token)
+}
+
+// Pull the token JSON out of `rclone authorize` output
+pub fn parse_authorize_output(output: &str) -> Option<String> {
+    // Newer rclone prints the token between "--->" and "<---End paste"
+    if let Some(start) = output.find(TOKEN_START) {
+        let rest = &output[start + TOKEN_START.len()..];
+        let end = rest.find(TOKEN_END).unwrap_or(rest.len());
+        let token = rest[..end].trim();
+        if !token.is_empty() {
+            return Some(token.to_string());
+        }
+    }
+
+    // Otherwise take the first line that looks like a JSON object
+    output
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| line.starts_with('{') && line.ends_with('}'))
+        .map(|line| line.to_string())
+}
+
+// Check a token is a JSON object and put it on one line, as rclone.conf values can't span lines
+pub fn normalize_token(token: &str) -> Result<String, String> {
+    let value: serde_json::Value = serde_json::from_str(token.trim())
+        .map_err(|e| format!("OAuth token is not valid JSON: {}", e))?;
+
+    if !value.is_object() {
+        return Err("OAuth token must be a JSON object".to_string());
+    }
+
+    Ok(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_token_between_the_paste_markers() {
+        let output = "Paste the following into your remote machine --->\n{\"access_token\":\"a\",\"expiry\":\"2030-01-01T00:00:00Z\"}\n<---End paste\n";
+        assert_eq!(
+            parse_authorize_output(output).as_deref(),
+            Some(r#"{"access_token":"a","expiry":"2030-01-01T00:00:00Z"}"#)
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_first_json_line() {
+        let output = "If your browser doesn't open automatically go to...\n  {\"access_token\":\"b\"}  \nSuccess\n";
+        assert_eq!(parse_authorize_output(output).as_deref(), Some(r#"{"access_token":"b"}"#));
+        assert_eq!(parse_authorize_output("---><---End paste\nno token here"), None);
+    }
+
+    #[test]
+    fn normalizes_tokens_to_one_line() {
+        assert_eq!(normalize_token(" {\n  \"access_token\": \"a\"\n}\n").unwrap(), r#"{"access_token":"a"}"#);
+        assert!(normalize_token("not json").is_err());
+        assert!(normalize_token("[1, 2]").is_err());
+        assert!(normalize_token("\"a string\"").is_err());
+    }
+}
@@ -0,0 +1,539 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// Version of the plugin config.json format understood by this build.
+// Files without a schema_version predate the field and are treated as version 1.
+pub const PLUGIN_SCHEMA_VERSION: u32 = 1;
+
+fn default_schema_version() -> u32 {
+    1
+}
+
+// Field types the frontend knows how to render
+// oauth_token fields left empty are filled in by running `rclone authorize` when the remote is added.
+// remote_ref/remote_refs pick one or more existing remotes, their options are filled from the config.
+pub const PLUGIN_FIELD_TYPES: &[&str] = &["text", "number", "password", "file", "checkbox", "oauth_token", "remote_ref", "remote_refs"];
+
+// Plugin structure definition
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+pub struct PluginField {
+    pub name: String,
+    pub display_name: String,
+    #[serde(rename = "type")]
+    pub field_type: String,
+    pub required: bool,
+    #[serde(default)]
+    pub default: String,
+    #[serde(default)]
+    pub placeholder: String,
+    #[serde(default)]
+    pub tooltip: String,
+    // Choices offered for the field
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+pub struct Plugin {
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
+    pub name: String,
+    pub display_name: String,
+    pub description: String,
+    pub version: String,
+    pub author: String,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default)]
+    pub basic_fields: Vec<PluginField>,
+    #[serde(default)]
+    pub advanced_fields: Vec<PluginField>,
+    #[serde(default)]
+    pub validation: HashMap<String, serde_json::Value>,
+    // Where this plugin was loaded from - filled in by the registry, never read from config.json
+    #[serde(default, skip_deserializing)]
+    #[schemars(skip)]
+    pub source: String,
+    #[serde(default, skip_deserializing)]
+    #[schemars(skip)]
+    pub source_path: String,
+    // config.json files of the same plugin that were hidden by this one
+    #[serde(default, skip_deserializing)]
+    #[schemars(skip)]
+    pub shadowed: Vec<String>,
+}
+
+// A directory that may contain plugins, one sub-directory with a config.json per plugin
+#[derive(Serialize, Clone)]
+pub struct PluginDir {
+    pub source: String,
+    pub path: PathBuf,
+}
+
+// A plugin config.json that could not be loaded
+#[derive(Serialize, Clone)]
+pub struct PluginError {
+    pub path: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl PluginError {
+    fn new(path: &Path, message: String) -> PluginError {
+        PluginError {
+            path: path.to_string_lossy().to_string(),
+            line: None,
+            column: None,
+            message,
+        }
+    }
+
+    fn from_json(path: &Path, e: &serde_json::Error) -> PluginError {
+        PluginError {
+            path: path.to_string_lossy().to_string(),
+            line: Some(e.line()),
+            column: Some(e.column()),
+            message: e.to_string(),
+        }
+    }
+}
+
+// All plugins found on this machine, after applying override precedence.
+// Broken plugin files are skipped and collected in `plugin_errors` instead of failing the whole load.
+#[derive(Serialize)]
+pub struct PluginRegistry {
+    pub plugins: Vec<Plugin>,
+    pub plugin_errors: Vec<PluginError>,
+}
+
+impl PluginRegistry {
+    // Directories searched for plugins, highest precedence first.
+    // A plugin found in an earlier directory overrides one with the same name in a later one:
+    //   1. user      - $XDG_DATA_HOME/de_rclone/plugins (~/.local/share/de_rclone/plugins)
+    //   2. system    - /usr/share/de_rclone/plugins
+    //   3. bundled   - plugins next to the executable (AppImage/prod)
+    //   4. dev       - ../plugins and ./plugins relative to the current directory
+    pub fn search_dirs() -> Vec<PluginDir> {
+        let mut search_dirs = Vec::new();
+
+        if let Some(data_dir) = dirs::data_dir() {
+            search_dirs.push(PluginDir {
+                source: "user".to_string(),
+                path: data_dir.join("de_rclone").join("plugins"),
+            });
+        }
+
+        search_dirs.push(PluginDir {
+            source: "system".to_string(),
+            path: PathBuf::from("/usr/share/de_rclone/plugins"),
+        });
+
+        if let Ok(exe_path) = std::env::current_exe() {
+            if let Some(parent) = exe_path.parent() {
+                search_dirs.push(PluginDir {
+                    source: "bundled".to_string(),
+                    path: parent.join("plugins"),
+                });
+            }
+        }
+
+        if let Ok(current_dir) = std::env::current_dir() {
+            search_dirs.push(PluginDir {
+                source: "dev".to_string(),
+                path: current_dir.join("..").join("plugins"),
+            });
+            search_dirs.push(PluginDir {
+                source: "dev".to_string(),
+                path: current_dir.join("plugins"),
+            });
+        }
+
+        search_dirs
+    }
+
+    // Scan every search directory and build the registry
+    pub fn load() -> PluginRegistry {
+        let mut plugins: Vec<Plugin> = Vec::new();
+        let mut errors: Vec<PluginError> = Vec::new();
+        let mut scanned: Vec<PathBuf> = Vec::new();
+
+        for dir in Self::search_dirs() {
+            // Skip missing directories and ones already scanned through another route
+            // (e.g. ./plugins being the same directory as the one next to the executable)
+            let canonical = match dir.path.canonicalize() {
+                Ok(path) if path.is_dir() => path,
+                _ => continue,
+            };
+            if scanned.contains(&canonical) {
+                continue;
+            }
+            scanned.push(canonical);
+
+            let config_files = match plugin_config_files(&dir.path) {
+                Ok(files) => files,
+                Err(e) => {
+                    errors.push(e);
+                    continue;
+                }
+            };
+
+            for config_path in config_files {
+                let mut plugin = match read_plugin_file(&config_path) {
+                    Ok(plugin) => plugin,
+                    Err(e) => {
+                        eprintln!("Skipping plugin {}: {}", e.path, e.message);
+                        errors.push(e);
+                        continue;
+                    }
+                };
+                let source_path = config_path.to_string_lossy().to_string();
+
+                // Already provided by a directory with higher precedence
+                if let Some(existing) = plugins.iter_mut().find(|p| p.name == plugin.name) {
+                    existing.shadowed.push(source_path);
+                    continue;
+                }
+
+                plugin.source = dir.source.clone();
+                plugin.source_path = source_path;
+                plugins.push(plugin);
+            }
+        }
+
+        plugins.sort_by(|a, b| a.name.cmp(&b.name));
+
+        PluginRegistry { plugins, plugin_errors: errors }
+    }
+
+    // Offer the configured remotes as the choices of remote reference fields
+    pub fn fill_remote_choices(&mut self, remote_names: &[String]) {
+        for plugin in &mut self.plugins {
+            for field in plugin.basic_fields.iter_mut().chain(plugin.advanced_fields.iter_mut()) {
+                if field.field_type == "remote_ref" || field.field_type == "remote_refs" {
+                    field.options = remote_names.to_vec();
+                }
+            }
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Plugin> {
+        self.plugins.iter().find(|p| p.name == name)
+    }
+
+    // Load error for a plugin directory named `name`, used to explain why a plugin is missing
+    pub fn find_error(&self, name: &str) -> Option<&PluginError> {
+        self.plugin_errors.iter().find(|e| {
+            Path::new(&e.path)
+                .parent()
+                .and_then(|dir| dir.file_name())
+                .map(|dir_name| dir_name == name)
+                .unwrap_or(false)
+        })
+    }
+}
+
+// List <dir>/<plugin>/config.json files in a stable order
+fn plugin_config_files(dir: &Path) -> Result<Vec<PathBuf>, PluginError> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| PluginError::new(dir, format!("Failed to read plugins directory: {}", e)))?;
+
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let config_path = entry.path().join("config.json");
+        if config_path.is_file() {
+            files.push(config_path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+fn read_plugin_file(config_path: &Path) -> Result<Plugin, PluginError> {
+    let config_content = std::fs::read_to_string(config_path)
+        .map_err(|e| PluginError::new(config_path, format!("Failed to read plugin config: {}", e)))?;
+
+    let value: serde_json::Value = serde_json::from_str(&config_content)
+        .map_err(|e| PluginError::from_json(config_path, &e))?;
+
+    let version = plugin_schema_version(&value)
+        .map_err(|message| PluginError::new(config_path, message))?;
+
+    if version == PLUGIN_SCHEMA_VERSION {
+        // Parse the text directly so type errors keep their line/column
+        serde_json::from_str(&config_content)
+            .map_err(|e| PluginError::from_json(config_path, &e))
+    } else {
+        serde_json::from_value(migrate_plugin(value, version))
+            .map_err(|e| PluginError::new(config_path, e.to_string()))
+    }
+}
+
+// Read schema_version from a parsed config.json, rejecting files newer than this build
+fn plugin_schema_version(value: &serde_json::Value) -> Result<u32, String> {
+    let version = match value.get("schema_version") {
+        None => default_schema_version(),
+        Some(v) => v.as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .filter(|v| *v > 0)
+            .ok_or_else(|| format!("schema_version must be a positive integer, got {}", v))?,
+    };
+
+    if version > PLUGIN_SCHEMA_VERSION {
+        return Err(format!(
+            "Plugin uses schema_version {} but this version of de_rclone only supports up to {}, please update de_rclone",
+            version, PLUGIN_SCHEMA_VERSION
+        ));
+    }
+
+    Ok(version)
+}
+
+// Steps that rewrite a config.json from schema_version N to N + 1, indexed by N - 1.
+// Each change to the plugin format bumps PLUGIN_SCHEMA_VERSION and appends a step here.
+const PLUGIN_MIGRATIONS: &[fn(serde_json::Value) -> serde_json::Value] = &[];
+
+// Rewrite an older config.json into the current format, one version at a time
+fn migrate_plugin(mut value: serde_json::Value, from_version: u32) -> serde_json::Value {
+    for migrate in PLUGIN_MIGRATIONS.iter().skip(from_version as usize - 1) {
+        value = migrate(value);
+    }
+
+    if let Some(object) = value.as_object_mut() {
+        object.insert("schema_version".to_string(), serde_json::Value::from(PLUGIN_SCHEMA_VERSION));
+    }
+
+    value
+}
+
+// JSON Schema of the plugin config.json format, generated from the Plugin/PluginField types
+pub fn plugin_json_schema() -> serde_json::Value {
+    serde_json::to_value(schemars::schema_for!(Plugin)).unwrap_or_default()
+}
+
+// Property names a schema object accepts
+fn schema_properties<T: JsonSchema>() -> Vec<String> {
+    schemars::schema_for!(T)
+        .schema
+        .object
+        .map(|object| object.properties.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+// Something wrong (error) or suspicious (warning) in a plugin file
+#[derive(Serialize, Clone)]
+pub struct PluginIssue {
+    pub severity: String,
+    // Where in the file, e.g. "basic_fields[2].name"
+    pub location: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl PluginIssue {
+    fn error(location: &str, message: String) -> PluginIssue {
+        PluginIssue {
+            severity: "error".to_string(),
+            location: location.to_string(),
+            line: None,
+            column: None,
+            message,
+        }
+    }
+
+    fn warning(location: &str, message: String) -> PluginIssue {
+        PluginIssue {
+            severity: "warning".to_string(),
+            ..PluginIssue::error(location, message)
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PluginValidation {
+    pub path: String,
+    pub valid: bool,
+    pub plugin_name: Option<String>,
+    pub issues: Vec<PluginIssue>,
+}
+
+// Check a plugin config.json (or a plugin directory containing one) without installing it:
+// it must parse, should not contain keys de_rclone ignores, and its fields must be options
+// the installed rclone backend actually accepts
+pub async fn validate_plugin_file(path: &Path) -> PluginValidation {
+    let config_path = if path.is_dir() { path.join("config.json") } else { path.to_path_buf() };
+    let mut issues = Vec::new();
+
+    let plugin = match read_plugin_file(&config_path) {
+        Ok(plugin) => Some(plugin),
+        Err(e) => {
+            issues.push(PluginIssue {
+                line: e.line,
+                column: e.column,
+                ..PluginIssue::error("", e.message)
+            });
+            None
+        }
+    };
+
+    // Keys serde silently ignores are usually typos
+    if let Ok(value) = std::fs::read_to_string(&config_path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).map_err(|e| e.to_string()))
+    {
+        issues.extend(unknown_keys(&value));
+    }
+
+    if let Some(plugin) = &plugin {
+        issues.extend(check_fields(plugin));
+
+        match rclone_backend_options(&plugin.name).await {
+            Ok(options) => issues.extend(check_backend_options(plugin, &options)),
+            Err(message) => issues.push(PluginIssue::warning("name", message)),
+        }
+    }
+
+    PluginValidation {
+        path: config_path.to_string_lossy().to_string(),
+        valid: !issues.iter().any(|issue| issue.severity == "error"),
+        plugin_name: plugin.map(|p| p.name),
+        issues,
+    }
+}
+
+fn unknown_keys(value: &serde_json::Value) -> Vec<PluginIssue> {
+    let mut issues = Vec::new();
+    let plugin_keys = schema_properties::<Plugin>();
+    let field_keys = schema_properties::<PluginField>();
+
+    let Some(object) = value.as_object() else {
+        return issues;
+    };
+
+    for key in object.keys() {
+        if !plugin_keys.contains(key) {
+            issues.push(PluginIssue::warning(key, format!("Unknown key '{}' is ignored", key)));
+        }
+    }
+
+    for list in ["basic_fields", "advanced_fields"] {
+        let Some(fields) = object.get(list).and_then(|v| v.as_array()) else {
+            continue;
+        };
+        for (index, field) in fields.iter().enumerate() {
+            let Some(field) = field.as_object() else {
+                continue;
+            };
+            for key in field.keys() {
+                if !field_keys.contains(key) {
+                    issues.push(PluginIssue::warning(
+                        &format!("{}[{}].{}", list, index, key),
+                        format!("Unknown field key '{}' is ignored", key),
+                    ));
+                }
+            }
+        }
+    }
+
+    issues
+}
+
+// Checks that only need the plugin itself
+fn check_fields(plugin: &Plugin) -> Vec<PluginIssue> {
+    let mut issues = Vec::new();
+    let mut seen: Vec<&str> = Vec::new();
+
+    for (list, fields) in [("basic_fields", &plugin.basic_fields), ("advanced_fields", &plugin.advanced_fields)] {
+        for (index, field) in fields.iter().enumerate() {
+            let location = format!("{}[{}]", list, index);
+
+            if seen.contains(&field.name.as_str()) {
+                issues.push(PluginIssue::error(&location, format!("Field '{}' is defined more than once", field.name)));
+            }
+            seen.push(&field.name);
+
+            if !PLUGIN_FIELD_TYPES.contains(&field.field_type.as_str()) {
+                issues.push(PluginIssue::warning(
+                    &format!("{}.type", location),
+                    format!("Unknown field type '{}', expected one of: {}", field.field_type, PLUGIN_FIELD_TYPES.join(", ")),
+                ));
+            }
+        }
+    }
+
+    for key in plugin.validation.keys() {
+        if !seen.contains(&key.as_str()) {
+            issues.push(PluginIssue::warning(
+                &format!("validation.{}", key),
+                format!("Validation rule for '{}' does not match any field", key),
+            ));
+        }
+    }
+
+    issues
+}
+
+// Checks against what `rclone config providers` reports for the backend
+fn check_backend_options(plugin: &Plugin, options: &[String]) -> Vec<PluginIssue> {
+    let mut issues = Vec::new();
+
+    for (list, fields) in [("basic_fields", &plugin.basic_fields), ("advanced_fields", &plugin.advanced_fields)] {
+        for (index, field) in fields.iter().enumerate() {
+            // remote_name becomes the section name rather than an option
+            if field.name == "remote_name" {
+                continue;
+            }
+            if !options.contains(&field.name) {
+                issues.push(PluginIssue::error(
+                    &format!("{}[{}].name", list, index),
+                    format!("rclone backend '{}' has no option '{}'", plugin.name, field.name),
+                ));
+            }
+        }
+    }
+
+    issues
+}
+
+// Option names accepted by an rclone backend, from `rclone config providers`
+async fn rclone_backend_options(backend: &str) -> Result<Vec<String>, String> {
+    let providers = rclone_providers()
+        .await
+        .map_err(|e| format!("Could not check options against rclone: {}", e))?;
+
+    let provider = providers
+        .iter()
+        .find(|p| p.get("Name").and_then(|n| n.as_str()) == Some(backend))
+        .ok_or_else(|| format!("Installed rclone has no backend named '{}'", backend))?;
+
+    Ok(provider
+        .get("Options")
+        .and_then(|o| o.as_array())
+        .map(|options| {
+            options
+                .iter()
+                .filter_map(|o| o.get("Name").and_then(|n| n.as_str()))
+                .map(|n| n.to_string())
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+// Every backend the installed rclone supports, as printed by `rclone config providers`
+pub async fn rclone_providers() -> Result<Vec<serde_json::Value>, String> {
+    let mut cmd = tokio::process::Command::new("rclone");
+    cmd.args(["config", "providers"]);
+    let output = crate::runner::output("providers", None, cmd).await?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("rclone config providers failed: {}", stderr));
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse rclone config providers output: {}", e))
+}
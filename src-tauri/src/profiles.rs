@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::{expand_tilde_path, settings};
+
+// A named rclone config with the settings that go with it
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Profile {
+    pub name: String,
+    // May start with "~/"
+    pub config_path: String,
+    // Remotes are mounted at <mount_dir>/<remote>, ~/mnt when not set. Profiles with
+    // remotes of the same name need different ones.
+    #[serde(default)]
+    pub mount_dir: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ProfileList {
+    pub active: Option<String>,
+    pub profiles: Vec<Profile>,
+}
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+impl Profile {
+    fn default_profile() -> Profile {
+        Profile {
+            name: DEFAULT_PROFILE.to_string(),
+            config_path: "~/.config/rclone/rclone.conf".to_string(),
+            mount_dir: None,
+        }
+    }
+
+    pub fn resolved_config_path(&self) -> Result<PathBuf, String> {
+        expand_tilde_path(&self.config_path)
+    }
+
+    pub fn resolved_mount_dir(&self) -> Result<PathBuf, String> {
+        match &self.mount_dir {
+            Some(dir) => expand_tilde_path(dir),
+            None => expand_tilde_path("~/mnt"),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Profile name cannot be empty".to_string());
+        }
+        if self.config_path.trim().is_empty() {
+            return Err(format!("Profile '{}' needs a config path", self.name));
+        }
+        Ok(())
+    }
+}
+
+// A default profile for rclone's standard config until any are saved, and a valid active one
+fn normalize(list: &mut ProfileList) {
+    if list.profiles.is_empty() {
+        list.profiles.push(Profile::default_profile());
+    }
+    if !list.active.as_ref().is_some_and(|active| list.profiles.iter().any(|p| &p.name == active)) {
+        list.active = list.profiles.first().map(|p| p.name.clone());
+    }
+}
+
+// All profiles, kept in the settings
+pub fn list() -> Result<ProfileList, String> {
+    let mut list = settings::load()?.profiles;
+    normalize(&mut list);
+    Ok(list)
+}
+
+// Change the profiles and save the settings
+fn modify<T>(change: impl FnOnce(&mut ProfileList) -> Result<T, String>) -> Result<T, String> {
+    settings::modify(|settings| {
+        normalize(&mut settings.profiles);
+        change(&mut settings.profiles)
+    })
+}
+
+// The profile commands fall back to when no config path is given
+pub fn active() -> Option<Profile> {
+    let list = list().ok()?;
+    let active = list.active?;
+    list.profiles.into_iter().find(|p| p.name == active)
+}
+
+// The profile using a config file, to find e.g. where its remotes are mounted
+pub fn for_config(config_path: &Path) -> Option<Profile> {
+    let list = list().ok()?;
+    list.profiles
+        .into_iter()
+        .find(|p| p.resolved_config_path().is_ok_and(|path| path == config_path))
+}
+
+// Add a profile, or replace the one with the same name
+pub fn save(profile: Profile) -> Result<Profile, String> {
+    profile.validate()?;
+
+    modify(|list| {
+        match list.profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile.clone(),
+            None => list.profiles.push(profile.clone()),
+        }
+        Ok(profile)
+    })
+}
+
+pub fn delete(name: &str) -> Result<(), String> {
+    modify(|list| {
+        if list.profiles.len() == 1 && list.profiles[0].name == name {
+            return Err("Cannot delete the last profile".to_string());
+        }
+        let before = list.profiles.len();
+        list.profiles.retain(|p| p.name != name);
+        if list.profiles.len() == before {
+            return Err(format!("Profile '{}' not found", name));
+        }
+        normalize(list);
+        Ok(())
+    })
+}
+
+pub fn switch(name: &str) -> Result<Profile, String> {
+    modify(|list| {
+        let profile = list
+            .profiles
+            .iter()
+            .find(|p| p.name == name)
+            .cloned()
+            .ok_or_else(|| format!("Profile '{}' not found", name))?;
+        list.active = Some(profile.name.clone());
+        Ok(profile)
+    })
+}
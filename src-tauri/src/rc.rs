@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use tokio::process::Command;
+
+use crate::{runner, transfer};
+
+// Where an rclone remote control server listens. Calls are JSON posts with basic auth.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct RcEndpoint {
+    pub url: String,
+    #[serde(default)]
+    pub user: String,
+    #[serde(default)]
+    pub pass: String,
+}
+
+impl RcEndpoint {
+    // Random credentials for the rc server of a process we start ourselves. The url is
+    // filled in once rclone has bound a port and logged it, see `serving_url`.
+    pub fn local() -> Result<RcEndpoint, String> {
+        Ok(RcEndpoint {
+            url: String::new(),
+            user: random_token()?,
+            pass: random_token()?,
+        })
+    }
+
+    // Make `rclone rcd`, or any rclone command with --rc, serve on a localhost port of
+    // rclone's choosing. The credentials go through the environment since anyone can read
+    // the command line of a process.
+    pub fn serve(&self, cmd: &mut Command) {
+        cmd.arg("--rc-addr")
+            .arg("127.0.0.1:0")
+            .env("RCLONE_RC_USER", &self.user)
+            .env("RCLONE_RC_PASS", &self.pass);
+    }
+
+    pub fn with_url(&self, url: String) -> RcEndpoint {
+        RcEndpoint { url, ..self.clone() }
+    }
+
+    // Call an rc method, e.g. "core/bwlimit", with JSON parameters
+    pub async fn call(&self, method: &str, params: Value, operation_id: Option<String>) -> Result<Value, String> {
+        let operation = runner::Operation::start("rc", operation_id)?;
+        let mut request = CLIENT.post(format!("{}{}", self.url, method)).json(&params);
+        if !self.user.is_empty() {
+            request = request.basic_auth(&self.user, Some(&self.pass));
+        }
+        if let Some(timeout) = runner::timeouts().get("rc") {
+            request = request.timeout(timeout);
+        }
+
+        let reply = async {
+            let response = request.send().await.map_err(|e| format!("rc {} failed: {}", method, e))?;
+            let status = response.status();
+            let body = response.text().await.map_err(|e| format!("rc {} failed: {}", method, e))?;
+
+            if !status.is_success() {
+                // Errors of the method come back as JSON with an "error" field, those of
+                // the server (e.g. bad credentials) as text
+                let error = serde_json::from_str::<Value>(&body)
+                    .ok()
+                    .and_then(|reply| reply.get("error").and_then(Value::as_str).map(String::from))
+                    .unwrap_or_else(|| format!("{} {}", status, body.trim()));
+                return Err(format!("rc {} failed: {}", method, error));
+            }
+
+            if body.trim().is_empty() {
+                return Ok(Value::Object(Default::default()));
+            }
+            serde_json::from_str(&body).map_err(|e| format!("Failed to parse reply of rc {}: {}", method, e))
+        };
+
+        tokio::select! {
+            reply = reply => reply,
+            e = operation.cancelled() => Err(e.into()),
+        }
+    }
+}
+
+// The address in rclone's "Serving remote control on http://127.0.0.1:41234/" log line,
+// plain or JSON. Newer versions print a list, "[http://127.0.0.1:41234/]".
+pub fn serving_url(log_line: &str) -> Option<String> {
+    let msg = match transfer::parse_log_line(log_line) {
+        Some(entry) => entry.msg,
+        None => log_line.to_string(),
+    };
+    let (_, rest) = msg.split_once("Serving remote control on ")?;
+    let url = rest.split_whitespace().next()?.trim_matches(['[', ']']);
+    if !url.starts_with("http") {
+        return None;
+    }
+    Some(if url.ends_with('/') { url.to_string() } else { format!("{}/", url) })
+}
+
+// $XDG_RUNTIME_DIR/de_rclone, for state that only lives as long as the processes it describes
+pub fn runtime_dir() -> Result<PathBuf, String> {
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .map(|dir| dir.join("de_rclone"))
+        .ok_or_else(|| "Could not determine the runtime directory".to_string())
+}
+
+// Where a process we start logs when its stderr isn't ours to read, e.g. `rclone mount --daemon`
+pub fn log_file(name: &str) -> Result<PathBuf, String> {
+    let dir = runtime_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    Ok(dir.join(format!("{}.log", name)))
+}
+
+// Shared by all calls so connections to the same server are reused
+static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(reqwest::Client::new);
+
+fn random_token() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    std::fs::File::open("/dev/urandom")
+        .and_then(|mut urandom| urandom.read_exact(&mut bytes))
+        .map_err(|e| format!("Failed to generate credentials: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// An rclone process of ours that serves rc, with the paths it works on
+#[derive(Clone)]
+pub struct RcProcess {
+    pub endpoint: RcEndpoint,
+    pub targets: Vec<String>,
+}
+
+// Running mounts and jobs we can still talk to, keyed by "mount:<remote_key>" (see rclone_config) or
+// the job's operation id
+static PROCESSES: LazyLock<Mutex<HashMap<String, RcProcess>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+pub fn register(key: &str, endpoint: RcEndpoint, targets: Vec<String>) {
+    let mut processes = PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+    processes.insert(key.to_string(), RcProcess { endpoint, targets });
+}
+
+pub fn unregister(key: &str) {
+    let mut processes = PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+    processes.remove(key);
+}
+
+pub fn processes() -> Vec<(String, RcProcess)> {
+    let processes = PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+    processes.iter().map(|(key, process)| (key.clone(), process.clone())).collect()
+}
+
+// Unregisters a job's rc endpoint when the run ends, however it ends
+pub struct Registration(pub String);
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        unregister(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_serving_url_from_plain_and_json_logs() {
+        assert_eq!(
+            serving_url("2024/01/01 12:00:00 NOTICE: Serving remote control on http://127.0.0.1:41234/").as_deref(),
+            Some("http://127.0.0.1:41234/")
+        );
+        assert_eq!(
+            serving_url(r#"{"level":"notice","msg":"Serving remote control on [http://127.0.0.1:41234]"}"#).as_deref(),
+            Some("http://127.0.0.1:41234/")
+        );
+        assert_eq!(serving_url("NOTICE: Serving remote control on"), None);
+        assert_eq!(serving_url("NOTICE: something else"), None);
+    }
+}
@@ -0,0 +1,598 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command};
+
+use crate::filters;
+use crate::rc::{self, RcEndpoint};
+use crate::rclone_config::{resolve_config_path, target_remote, validate_remote_name, ConfigSection};
+use crate::settings::{self, BackendSettings};
+use crate::transfer::TransferStats;
+use crate::{bandwidth, runner};
+
+// How long a freshly started rcd gets to answer
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How rclone is run: a new process per operation, one `rclone rcd` serving everything, or
+// an rcd running elsewhere (e.g. under systemd on a server) that we only talk to
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    #[default]
+    Cli,
+    Rcd,
+    Attached,
+}
+
+#[derive(Serialize)]
+pub struct BackendStatus {
+    pub backend: BackendKind,
+    pub url: Option<String>,
+    pub pid: Option<u32>,
+    pub version: Option<String>,
+    pub config_path: Option<PathBuf>,
+}
+
+// A mount as listed for the UI, with the backend that owns it
+#[derive(Serialize)]
+pub struct MountInfo {
+    pub fs: String,
+    pub mount_point: String,
+    // "rcd" or "attached" for mounts served by the daemon, "cli" for `rclone mount` processes
+    pub backend: String,
+    pub mounted_on: Option<String>,
+}
+
+// An async job of the daemon as reported by job/status, plus its stats while it runs
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default, rename_all(deserialize = "camelCase"))]
+pub struct RcJob {
+    pub id: i64,
+    pub group: String,
+    pub start_time: String,
+    pub end_time: String,
+    // Seconds
+    pub duration: f64,
+    pub error: String,
+    pub finished: bool,
+    pub success: bool,
+    #[serde(skip_deserializing)]
+    pub stats: Option<TransferStats>,
+}
+
+// The running daemon. Its connection details are also written to the runtime dir, so a
+// daemon left running by an earlier session (and its mounts) can be picked up again.
+#[derive(Serialize, Deserialize)]
+struct DaemonInfo {
+    endpoint: RcEndpoint,
+    pid: Option<u32>,
+    // None for an attached daemon, its config lives wherever it runs
+    config_path: Option<PathBuf>,
+    #[serde(default)]
+    attached: bool,
+}
+
+struct Daemon {
+    info: DaemonInfo,
+    // None for a daemon started by an earlier session
+    child: Option<Child>,
+}
+
+static DAEMON: LazyLock<Mutex<Option<Daemon>>> = LazyLock::new(|| Mutex::new(None));
+
+// The rc endpoint to use instead of spawning rclone, None while using the CLI
+pub fn active() -> Option<RcEndpoint> {
+    let mut daemon = DAEMON.lock().unwrap_or_else(|e| e.into_inner());
+    let alive = match daemon.as_mut() {
+        Some(Daemon { child: Some(child), .. }) => matches!(child.try_wait(), Ok(None)),
+        Some(Daemon { child: None, .. }) => true,
+        None => false,
+    };
+    if !alive {
+        *daemon = None;
+        return None;
+    }
+    daemon.as_ref().map(|daemon| daemon.info.endpoint.clone())
+}
+
+// The daemon's endpoint if it serves `config_path`, calls for another config use the CLI
+pub fn active_for(config_path: &Path) -> Option<RcEndpoint> {
+    let endpoint = active()?;
+    let daemon = DAEMON.lock().unwrap_or_else(|e| e.into_inner());
+    daemon
+        .as_ref()
+        .filter(|daemon| daemon.info.config_path.as_deref() == Some(config_path))
+        .map(|_| endpoint)
+}
+
+// The endpoint of a daemon we started on this machine, whatever its config
+pub fn local_active() -> Option<RcEndpoint> {
+    active().filter(|_| !is_attached())
+}
+
+// The config the daemon we started runs on, None for an attached one
+pub fn config_path() -> Option<PathBuf> {
+    active()?;
+    let daemon = DAEMON.lock().unwrap_or_else(|e| e.into_inner());
+    daemon.as_ref().and_then(|daemon| daemon.info.config_path.clone())
+}
+
+pub fn is_attached() -> bool {
+    let daemon = DAEMON.lock().unwrap_or_else(|e| e.into_inner());
+    daemon.as_ref().is_some_and(|daemon| daemon.info.attached)
+}
+
+// $XDG_RUNTIME_DIR/de_rclone/rcd.json, readable only by the user since it holds the credentials
+fn daemon_file() -> Result<PathBuf, String> {
+    Ok(rc::runtime_dir()?.join("rcd.json"))
+}
+
+fn write_daemon_file(info: &DaemonInfo) -> Result<(), String> {
+    let path = daemon_file()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let content = serde_json::to_string(info).map_err(|e| format!("Failed to serialize daemon info: {}", e))?;
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+// Remember the backend in the settings so the next session starts with it
+fn remember(kind: BackendKind, attach: Option<RcEndpoint>) -> Result<(), String> {
+    settings::modify(|settings| {
+        settings.backend = BackendSettings { kind, attach };
+        Ok(())
+    })
+}
+
+fn read_daemon_file() -> Option<DaemonInfo> {
+    let content = fs::read_to_string(daemon_file().ok()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+// Switch to the rcd backend, reusing a daemon of an earlier session for the same config
+pub async fn start(config_path: &Path) -> Result<BackendStatus, String> {
+    if let Some(endpoint) = active() {
+        let same_config = DAEMON
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .is_some_and(|daemon| daemon.info.config_path.as_deref() == Some(config_path));
+        if same_config {
+            return status().await;
+        }
+        return Err(format!("rclone rcd is already running at {}, stop it first", endpoint.url));
+    }
+
+    if let Some(info) = read_daemon_file().filter(|info| info.config_path.as_deref() == Some(config_path)) {
+        if info.endpoint.call("rc/noop", json!({}), None).await.is_ok() {
+            *DAEMON.lock().unwrap_or_else(|e| e.into_inner()) = Some(Daemon { info, child: None });
+            remember(BackendKind::Rcd, None)?;
+            return status().await;
+        }
+    }
+
+    let credentials = RcEndpoint::local()?;
+    let mut cmd = Command::new("rclone");
+    cmd.arg("rcd")
+        .arg("--config")
+        .arg(config_path)
+        .args(bandwidth::load()?.args(&[]))
+        .stderr(Stdio::piped())
+        .kill_on_drop(false);
+    credentials.serve(&mut cmd);
+
+    let mut child = cmd.spawn().map_err(|e| format!("Failed to start rclone rcd: {}", e))?;
+
+    // rclone logs the port it bound once it serves
+    let mut lines = BufReader::new(child.stderr.take().ok_or("rclone rcd has no stderr")?).lines();
+    let mut log = Vec::new();
+    let url = tokio::time::timeout(STARTUP_TIMEOUT, async {
+        while let Ok(Some(line)) = lines.next_line().await {
+            if let Some(url) = rc::serving_url(&line) {
+                return Some(url);
+            }
+            log.push(line);
+        }
+        None
+    })
+    .await;
+    let url = match url {
+        Ok(Some(url)) => url,
+        Ok(None) => {
+            let _ = child.start_kill();
+            return Err(format!("rclone rcd exited right away: {}", log.join("\n")));
+        }
+        Err(_) => {
+            let _ = child.start_kill();
+            return Err("rclone rcd did not come up in time".to_string());
+        }
+    };
+    let endpoint = credentials.with_url(url);
+
+    // Keep reading so a full pipe never blocks the daemon
+    tokio::spawn(async move { while let Ok(Some(_)) = lines.next_line().await {} });
+
+    let info = DaemonInfo {
+        endpoint,
+        pid: child.id(),
+        config_path: Some(config_path.to_path_buf()),
+        attached: false,
+    };
+    write_daemon_file(&info)?;
+    *DAEMON.lock().unwrap_or_else(|e| e.into_inner()) = Some(Daemon { info, child: Some(child) });
+    remember(BackendKind::Rcd, None)?;
+
+    status().await
+}
+
+// Use an rcd that runs elsewhere, e.g. "http://server:5572/". With --rc-htpasswd on the
+// server the user and password are checked against that file, it's the same for us.
+pub async fn attach(url: &str, user: &str, pass: &str) -> Result<BackendStatus, String> {
+    if let Some(endpoint) = active() {
+        return Err(format!("Already using rclone rcd at {}, switch back to cli first", endpoint.url));
+    }
+
+    let url = url.trim();
+    if url.is_empty() {
+        return Err("The rc URL cannot be empty".to_string());
+    }
+    let mut url = if url.contains("://") { url.to_string() } else { format!("http://{}", url) };
+    if !url.ends_with('/') {
+        url.push('/');
+    }
+    let endpoint = RcEndpoint {
+        url,
+        user: user.to_string(),
+        pass: pass.to_string(),
+    };
+
+    endpoint
+        .call("rc/noop", json!({}), None)
+        .await
+        .map_err(|e| format!("Could not reach rclone rcd at {}: {}", endpoint.url, e))?;
+
+    remember(BackendKind::Attached, Some(endpoint.clone()))?;
+    let info = DaemonInfo {
+        endpoint,
+        pid: None,
+        config_path: None,
+        attached: true,
+    };
+    *DAEMON.lock().unwrap_or_else(|e| e.into_inner()) = Some(Daemon { info, child: None });
+
+    status().await
+}
+
+// Bring back the backend of the last session, called on startup
+pub async fn restore() {
+    let backend = match settings::load() {
+        Ok(settings) => settings.backend,
+        Err(e) => {
+            eprintln!("Failed to load settings: {}", e);
+            return;
+        }
+    };
+
+    let result = match (backend.kind, backend.attach) {
+        (BackendKind::Rcd, _) => match resolve_config_path(None) {
+            Ok(config_path) => start(&config_path).await.map(|_| ()),
+            Err(e) => Err(e),
+        },
+        (BackendKind::Attached, Some(endpoint)) => attach(&endpoint.url, &endpoint.user, &endpoint.pass).await.map(|_| ()),
+        _ => Ok(()),
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to restore the rclone rcd backend: {}", e);
+    }
+}
+
+// Switch back to the CLI backend. Stopping the daemon also unmounts everything it mounted,
+// so that needs `force` while it has mounts. An attached daemon keeps running, we only
+// forget about it.
+pub async fn stop(force: bool) -> Result<(), String> {
+    let Some(endpoint) = active() else {
+        return Ok(());
+    };
+
+    if is_attached() {
+        DAEMON.lock().unwrap_or_else(|e| e.into_inner()).take();
+        return remember(BackendKind::Cli, None);
+    }
+
+    if !force {
+        let mounts = list_mounts(&endpoint).await?;
+        if !mounts.is_empty() {
+            return Err(format!("rclone rcd still serves {} mounts, stopping it unmounts them", mounts.len()));
+        }
+    }
+
+    // core/quit answers before exiting, errors only mean it is already gone
+    let _ = endpoint.call("core/quit", json!({}), None).await;
+    if let Some(mut daemon) = DAEMON.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        if let Some(child) = daemon.child.as_mut() {
+            let _ = child.start_kill();
+        }
+    }
+    if let Ok(path) = daemon_file() {
+        let _ = fs::remove_file(path);
+    }
+    remember(BackendKind::Cli, None)
+}
+
+pub async fn status() -> Result<BackendStatus, String> {
+    let Some(endpoint) = active() else {
+        return Ok(BackendStatus {
+            backend: BackendKind::Cli,
+            url: None,
+            pid: None,
+            version: None,
+            config_path: None,
+        });
+    };
+
+    let version = endpoint
+        .call("core/version", json!({}), None)
+        .await?
+        .get("version")
+        .and_then(Value::as_str)
+        .map(String::from);
+
+    let daemon = DAEMON.lock().unwrap_or_else(|e| e.into_inner());
+    let info = daemon.as_ref().map(|daemon| &daemon.info);
+    Ok(BackendStatus {
+        backend: if info.is_some_and(|info| info.attached) { BackendKind::Attached } else { BackendKind::Rcd },
+        url: Some(endpoint.url),
+        pid: info.and_then(|info| info.pid),
+        version,
+        config_path: info.and_then(|info| info.config_path.clone()),
+    })
+}
+
+// Split "remote:dir/file" into the fs and path parameters rc calls take.
+// None for paths rc can't address the same way, e.g. relative local paths.
+pub fn split_fs(path: &str) -> Option<(String, String)> {
+    let path = path.trim();
+    match target_remote(path) {
+        Some(remote) => Some((format!("{}:", remote), path[remote.len() + 1..].trim_start_matches('/').to_string())),
+        None if path.starts_with('/') => Some(("/".to_string(), path.trim_start_matches('/').to_string())),
+        None => None,
+    }
+}
+
+// Mount through the daemon. With the config it runs on the remote's mount filters apply, an
+// attached daemon has no config here and can't read our filter files anyway.
+pub async fn mount(endpoint: &RcEndpoint, config_path: Option<&Path>, remote_name: &str, mount_point: &str, operation_id: Option<String>) -> Result<(), String> {
+    let mut params = json!({
+        "fs": format!("{}:", remote_name),
+        "mountPoint": mount_point,
+        // 2 is "writes", numbers work with every rclone version
+        "vfsOpt": { "CacheMode": 2 },
+    });
+    if let Some(config_path) = config_path {
+        let rules = filters::mount_filters(config_path, remote_name)?;
+        if let Some(filter) = filters::write_rc_filter(&filters::mount_filter_name(config_path, remote_name), &rules)? {
+            params["_filter"] = filter;
+        }
+    }
+
+    endpoint.call("mount/mount", params, operation_id).await.map(|_| ())
+}
+
+pub async fn unmount(endpoint: &RcEndpoint, mount_point: &str, operation_id: Option<String>) -> Result<(), String> {
+    endpoint
+        .call("mount/unmount", json!({ "mountPoint": mount_point }), operation_id)
+        .await
+        .map(|_| ())
+}
+
+pub async fn list_mounts(endpoint: &RcEndpoint) -> Result<Vec<MountInfo>, String> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct ListedMount {
+        fs: String,
+        mount_point: String,
+        #[serde(default)]
+        mounted_on: Option<String>,
+    }
+
+    let reply = endpoint.call("mount/listmounts", json!({}), None).await?;
+    let listed: Vec<ListedMount> = serde_json::from_value(reply.get("mountPoints").cloned().unwrap_or(Value::Null))
+        .unwrap_or_default();
+    let backend = if is_attached() { "attached" } else { "rcd" };
+
+    Ok(listed
+        .into_iter()
+        .map(|mount| MountInfo {
+            fs: mount.fs,
+            mount_point: mount.mount_point,
+            backend: backend.to_string(),
+            mounted_on: mount.mounted_on,
+        })
+        .collect())
+}
+
+// Jobs of the daemon newest first, including ones started by other clients
+pub async fn list_jobs(endpoint: &RcEndpoint) -> Result<Vec<RcJob>, String> {
+    let reply = endpoint.call("job/list", json!({}), None).await?;
+    let mut job_ids: Vec<i64> = serde_json::from_value(reply.get("jobids").cloned().unwrap_or(Value::Null)).unwrap_or_default();
+    job_ids.sort_unstable_by(|a, b| b.cmp(a));
+
+    let mut jobs = Vec::new();
+    for job_id in job_ids {
+        // Finished jobs expire on the daemon, one may be gone by now
+        let Ok(status) = endpoint.call("job/status", json!({ "jobid": job_id }), None).await else {
+            continue;
+        };
+        let mut job: RcJob = serde_json::from_value(status).map_err(|e| format!("Failed to parse rc job/status reply: {}", e))?;
+        if !job.finished {
+            let group = if job.group.is_empty() { format!("job/{}", job.id) } else { job.group.clone() };
+            let stats = match polled_stats(endpoint, job.id) {
+                Some(stats) => Some(stats),
+                None => endpoint.call("core/stats", json!({ "group": group }), None).await.ok(),
+            };
+            job.stats = stats.and_then(|stats| serde_json::from_value(stats).ok());
+        }
+        jobs.push(job);
+    }
+    Ok(jobs)
+}
+
+pub async fn stop_job(endpoint: &RcEndpoint, job_id: i64) -> Result<(), String> {
+    endpoint.call("job/stop", json!({ "jobid": job_id }), None).await.map(|_| ())
+}
+
+// Remotes in the daemon's own config
+pub async fn list_remotes(endpoint: &RcEndpoint) -> Result<Vec<String>, String> {
+    let reply = endpoint.call("config/listremotes", json!({}), None).await?;
+    Ok(serde_json::from_value(reply.get("remotes").cloned().unwrap_or(Value::Null)).unwrap_or_default())
+}
+
+// The daemon's config as it holds it, in the order config/listremotes gives
+pub async fn config_sections(endpoint: &RcEndpoint) -> Result<Vec<ConfigSection>, String> {
+    let names = list_remotes(endpoint).await?;
+    let dump = endpoint.call("config/dump", json!({}), None).await?;
+
+    Ok(names
+        .into_iter()
+        .map(|name| {
+            let options = match dump.get(&name) {
+                Some(Value::Object(options)) => options
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.as_str().map(String::from).unwrap_or_else(|| value.to_string())))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            ConfigSection { name, options }
+        })
+        .collect())
+}
+
+// One remote's options as the daemon has them
+pub async fn remote_config(endpoint: &RcEndpoint, name: &str) -> Result<HashMap<String, String>, String> {
+    if !list_remotes(endpoint).await?.iter().any(|remote| remote == name) {
+        return Err(format!("Remote '{}' not found in config", name));
+    }
+
+    match endpoint.call("config/get", json!({ "name": name }), None).await? {
+        Value::Object(options) => Ok(options
+            .into_iter()
+            .map(|(key, value)| {
+                let value = value.as_str().map(String::from).unwrap_or_else(|| value.to_string());
+                (key, value)
+            })
+            .collect()),
+        _ => Err(format!("Unexpected config/get response for '{}'", name)),
+    }
+}
+
+fn parameters(options: &[(String, String)]) -> Value {
+    Value::Object(options.iter().map(|(key, value)| (key.clone(), Value::String(value.clone()))).collect())
+}
+
+// Passwords in `options` are already obscured and the daemon must not ask anything
+fn config_opt() -> Value {
+    json!({ "nonInteractive": true, "noObscure": true })
+}
+
+// Add a remote through the daemon, refusing to overwrite an existing one like append_section
+pub async fn create_remote(endpoint: &RcEndpoint, name: &str, remote_type: &str, options: &[(String, String)]) -> Result<(), String> {
+    validate_remote_name(name)?;
+    if list_remotes(endpoint).await?.iter().any(|remote| remote == name) {
+        return Err(format!("Remote '{}' already exists", name));
+    }
+
+    let params = json!({ "name": name, "type": remote_type, "parameters": parameters(options), "opt": config_opt() });
+    endpoint.call("config/create", params, None).await.map(|_| ())
+}
+
+// Change some options of a remote through the daemon
+pub async fn update_remote(endpoint: &RcEndpoint, name: &str, options: &[(String, String)]) -> Result<(), String> {
+    let params = json!({ "name": name, "parameters": parameters(options), "opt": config_opt() });
+    endpoint.call("config/update", params, None).await.map(|_| ())
+}
+
+pub async fn delete_remote(endpoint: &RcEndpoint, name: &str) -> Result<(), String> {
+    endpoint.call("config/delete", json!({ "name": name }), None).await.map(|_| ())
+}
+
+// rclone FUSE mounts from /proc/mounts, which includes those of `rclone mount --daemon`
+pub fn system_mounts() -> Vec<MountInfo> {
+    let mounts = fs::read_to_string("/proc/mounts").unwrap_or_default();
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                [fs, mount_point, kind, ..] if *kind == "fuse.rclone" => Some(MountInfo {
+                    fs: fs.to_string(),
+                    // Spaces are escaped as \040
+                    mount_point: mount_point.replace("\\040", " "),
+                    backend: "cli".to_string(),
+                    mounted_on: None,
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+// Latest stats of the jobs `wait_for_job` is polling, keyed by daemon url and job id, so
+// listing jobs doesn't poll them a second time
+static POLLED_STATS: LazyLock<Mutex<HashMap<(String, i64), Value>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Forgets a job's polled stats however the wait ends
+struct PolledJob((String, i64));
+
+impl Drop for PolledJob {
+    fn drop(&mut self) {
+        POLLED_STATS.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.0);
+    }
+}
+
+fn polled_stats(endpoint: &RcEndpoint, job_id: i64) -> Option<Value> {
+    let polled = POLLED_STATS.lock().unwrap_or_else(|e| e.into_inner());
+    polled.get(&(endpoint.url.clone(), job_id)).cloned()
+}
+
+// Wait for an async rc job, reporting its stats every second, and stop it on cancellation.
+// Returns the final job/status reply.
+pub async fn wait_for_job(endpoint: &RcEndpoint, job_id: i64, operation: &runner::Operation, mut on_stats: impl FnMut(Value)) -> Result<Value, String> {
+    let key = (endpoint.url.clone(), job_id);
+    let _polled = PolledJob(key.clone());
+    let group = format!("job/{}", job_id);
+
+    loop {
+        let status = endpoint.call("job/status", json!({ "jobid": job_id }), None).await?;
+        if status.get("finished").and_then(Value::as_bool) == Some(true) {
+            return Ok(status);
+        }
+
+        if let Ok(stats) = endpoint.call("core/stats", json!({ "group": group }), None).await {
+            POLLED_STATS.lock().unwrap_or_else(|e| e.into_inner()).insert(key.clone(), stats.clone());
+            on_stats(stats);
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+            e = operation.cancelled() => {
+                let _ = endpoint.call("job/stop", json!({ "jobid": job_id }), None).await;
+                return Err(e.into());
+            }
+        }
+    }
+}
@@ -0,0 +1,307 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::process::{Output, Stdio};
+use std::sync::{LazyLock, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+
+// Per-operation time limits in seconds, 0 means no limit
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Timeouts {
+    pub mount: u64,
+    pub unmount: u64,
+    pub test: u64,
+    pub diagnose_step: u64,
+    pub obscure: u64,
+    // Waiting for the user to finish signing in in the browser
+    pub authorize: u64,
+    pub config: u64,
+    pub providers: u64,
+    pub version: u64,
+    pub usage: u64,
+    pub list: u64,
+    // Copies and moves take as long as the data needs, no limit by default
+    pub transfer: u64,
+    pub file_op: u64,
+    // Dry runs walk both sides completely
+    pub preview: u64,
+    pub systemd: u64,
+    pub rc: u64,
+    // `mountpoint` hangs on a stale FUSE mount
+    pub mount_check: u64,
+    pub crontab: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            mount: 90,
+            unmount: 30,
+            test: 60,
+            diagnose_step: 30,
+            obscure: 10,
+            authorize: 300,
+            config: 60,
+            providers: 15,
+            version: 10,
+            usage: 30,
+            list: 120,
+            transfer: 0,
+            file_op: 120,
+            preview: 600,
+            systemd: 30,
+            rc: 30,
+            mount_check: 5,
+            crontab: 10,
+        }
+    }
+}
+
+impl Timeouts {
+    pub fn get(&self, kind: &str) -> Option<Duration> {
+        let secs = match kind {
+            "mount" => self.mount,
+            "unmount" => self.unmount,
+            "test" => self.test,
+            "diagnose" => self.diagnose_step,
+            "obscure" => self.obscure,
+            "authorize" => self.authorize,
+            "config" => self.config,
+            "providers" => self.providers,
+            "version" => self.version,
+            "usage" => self.usage,
+            "list" => self.list,
+            "transfer" => self.transfer,
+            "file_op" => self.file_op,
+            "preview" => self.preview,
+            "systemd" => self.systemd,
+            "rc" => self.rc,
+            "mount_check" => self.mount_check,
+            "crontab" => self.crontab,
+            _ => 0,
+        };
+        (secs > 0).then(|| Duration::from_secs(secs))
+    }
+}
+
+// An operation currently running, as listed for the UI
+#[derive(Serialize, Clone)]
+pub struct OperationInfo {
+    pub id: String,
+    pub kind: String,
+    pub started_at: u64,
+}
+
+struct RunningOperation {
+    info: OperationInfo,
+    token: CancellationToken,
+}
+
+static TIMEOUTS: LazyLock<RwLock<Timeouts>> = LazyLock::new(|| RwLock::new(Timeouts::default()));
+static OPERATIONS: LazyLock<Mutex<HashMap<String, RunningOperation>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+pub fn timeouts() -> Timeouts {
+    TIMEOUTS.read().map(|t| t.clone()).unwrap_or_default()
+}
+
+pub fn set_timeouts(timeouts: Timeouts) {
+    if let Ok(mut current) = TIMEOUTS.write() {
+        *current = timeouts;
+    }
+}
+
+pub fn list_operations() -> Vec<OperationInfo> {
+    let operations = OPERATIONS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut list: Vec<OperationInfo> = operations.values().map(|op| op.info.clone()).collect();
+    list.sort_by_key(|op| op.started_at);
+    list
+}
+
+// Cancel a running operation, killing whatever process it is waiting on.
+// Returns false when no operation with that id is running.
+pub fn cancel(id: &str) -> bool {
+    let operations = OPERATIONS.lock().unwrap_or_else(|e| e.into_inner());
+    match operations.get(id) {
+        Some(op) => {
+            op.token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+// Why a command did not produce output
+pub enum RunError {
+    Spawn(String, std::io::Error),
+    TimedOut(String, Duration),
+    Cancelled(String),
+}
+
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunError::Spawn(program, e) => write!(f, "Failed to execute {}: {}", program, e),
+            RunError::TimedOut(kind, timeout) => write!(f, "{} timed out after {} seconds", kind, timeout.as_secs()),
+            RunError::Cancelled(id) => write!(f, "Operation {} was cancelled", id),
+        }
+    }
+}
+
+impl From<RunError> for String {
+    fn from(e: RunError) -> String {
+        e.to_string()
+    }
+}
+
+// A cancellable unit of work the UI can refer to by id. Several commands may run under one
+// operation, e.g. all steps of a diagnostics run. Unregistered when dropped.
+pub struct Operation {
+    id: String,
+    kind: String,
+    token: CancellationToken,
+}
+
+impl Operation {
+    // Register an operation under the id chosen by the UI, or a generated one
+    pub fn start(kind: &str, operation_id: Option<String>) -> Result<Operation, String> {
+        let id = operation_id.unwrap_or_else(|| generate_id(kind));
+        let token = CancellationToken::new();
+
+        let mut operations = OPERATIONS.lock().unwrap_or_else(|e| e.into_inner());
+        if operations.contains_key(&id) {
+            return Err(format!("Operation {} is already running", id));
+        }
+        operations.insert(id.clone(), RunningOperation {
+            info: OperationInfo {
+                id: id.clone(),
+                kind: kind.to_string(),
+                started_at: now_secs(),
+            },
+            token: token.clone(),
+        });
+
+        Ok(Operation { id, kind: kind.to_string(), token })
+    }
+
+    // Run a command to completion with this operation's default timeout
+    pub async fn output(&self, cmd: Command) -> Result<Output, RunError> {
+        let timeout = timeouts().get(&self.kind);
+        self.output_with_timeout(cmd, timeout).await
+    }
+
+    // Run a command to completion, killing it on timeout or cancellation
+    pub async fn output_with_timeout(&self, mut cmd: Command, timeout: Option<Duration>) -> Result<Output, RunError> {
+        let program = cmd.as_std().get_program().to_string_lossy().to_string();
+        cmd.kill_on_drop(true);
+
+        // Dropping the output future kills the child thanks to kill_on_drop
+        self.limit(&program, timeout, cmd.output()).await
+    }
+
+    // Run a command with this operation's default timeout, handing each stderr line to
+    // `on_line` as it is written. Only lines for which `on_line` returns true end up in the
+    // returned output, so periodic progress lines don't pile up during long transfers.
+    pub async fn output_streaming(&self, mut cmd: Command, mut on_line: impl FnMut(&str) -> bool) -> Result<Output, RunError> {
+        let program = cmd.as_std().get_program().to_string_lossy().to_string();
+        let timeout = timeouts().get(&self.kind);
+        cmd.kill_on_drop(true).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+        if self.token.is_cancelled() {
+            return Err(RunError::Cancelled(self.id.clone()));
+        }
+
+        let mut child = cmd.spawn().map_err(|e| RunError::Spawn(program.clone(), e))?;
+        let mut stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+
+        // The child is killed when it is dropped on timeout or cancellation
+        let run = async {
+            let read_stdout = async {
+                let mut buf = Vec::new();
+                if let Some(stdout) = stdout.as_mut() {
+                    stdout.read_to_end(&mut buf).await?;
+                }
+                Ok::<_, std::io::Error>(buf)
+            };
+            let read_stderr = async {
+                let mut kept = Vec::new();
+                if let Some(stderr) = stderr {
+                    let mut lines = BufReader::new(stderr).lines();
+                    while let Some(line) = lines.next_line().await? {
+                        if on_line(&line) {
+                            kept.extend_from_slice(line.as_bytes());
+                            kept.push(b'\n');
+                        }
+                    }
+                }
+                Ok::<_, std::io::Error>(kept)
+            };
+
+            let (stdout, stderr) = tokio::try_join!(read_stdout, read_stderr)?;
+            let status = child.wait().await?;
+            Ok(Output { status, stdout, stderr })
+        };
+
+        self.limit(&program, timeout, run).await
+    }
+
+    // Resolves when the operation is cancelled, for work that doesn't run through a Command
+    pub async fn cancelled(&self) -> RunError {
+        self.token.cancelled().await;
+        RunError::Cancelled(self.id.clone())
+    }
+
+    // Await `run` unless it takes longer than `timeout` or the operation is cancelled
+    async fn limit<T>(&self, program: &str, timeout: Option<Duration>, run: impl Future<Output = std::io::Result<T>>) -> Result<T, RunError> {
+        if self.token.is_cancelled() {
+            return Err(RunError::Cancelled(self.id.clone()));
+        }
+
+        let run = async {
+            match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, run)
+                    .await
+                    .map_err(|_| RunError::TimedOut(self.kind.clone(), timeout))?,
+                None => run.await,
+            }
+            .map_err(|e| RunError::Spawn(program.to_string(), e))
+        };
+
+        tokio::select! {
+            result = run => result,
+            _ = self.token.cancelled() => Err(RunError::Cancelled(self.id.clone())),
+        }
+    }
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        let mut operations = OPERATIONS.lock().unwrap_or_else(|e| e.into_inner());
+        operations.remove(&self.id);
+    }
+}
+
+// Run a single command as its own operation
+pub async fn output(kind: &str, operation_id: Option<String>, cmd: Command) -> Result<Output, String> {
+    let operation = Operation::start(kind, operation_id)?;
+    Ok(operation.output(cmd).await?)
+}
+
+pub fn generate_id(kind: &str) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!("{}-{}", kind, nanos)
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,523 @@
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::process::Command;
+
+use crate::jobs::{self, Job};
+use crate::runner;
+
+// How often the scheduler looks for due jobs
+const TICK: Duration = Duration::from_secs(20);
+
+// Shortest interval allowed for interval schedules
+const MIN_INTERVAL_SECS: u64 = 60;
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+// When a job runs on its own
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Schedule {
+    // Five field cron expression, "minute hour day-of-month month day-of-week", or @hourly, @daily, ...
+    Cron { expression: String },
+    // Every `seconds`, counted from the last run (or from app start)
+    Interval { seconds: u64 },
+}
+
+impl Schedule {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Schedule::Cron { expression } => match CronExpr::parse(expression)?.next_after(Local::now()) {
+                Some(_) => Ok(()),
+                None => Err(format!("'{}' never runs", expression)),
+            },
+            Schedule::Interval { seconds } if *seconds < MIN_INTERVAL_SECS => {
+                Err(format!("Interval must be at least {} seconds", MIN_INTERVAL_SECS))
+            }
+            Schedule::Interval { .. } => Ok(()),
+        }
+    }
+
+    // The first run after `after`, None when a cron expression never matches (e.g. "0 0 30 2 *")
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        match self {
+            Schedule::Cron { expression } => CronExpr::parse(expression).ok()?.next_after(after),
+            Schedule::Interval { seconds } => Some(after + chrono::Duration::seconds(*seconds as i64)),
+        }
+    }
+}
+
+// A parsed cron expression, one flag per allowed value of each field
+pub struct CronExpr {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    // 0 is Sunday
+    days_of_week: Vec<bool>,
+    // Cron runs on either day field when both are restricted, on both otherwise
+    days_of_month_restricted: bool,
+    days_of_week_restricted: bool,
+}
+
+impl CronExpr {
+    pub fn parse(expression: &str) -> Result<CronExpr, String> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("'{}' is not a cron expression, expected 5 fields", expression));
+        }
+
+        let mut days_of_week = parse_field(fields[4], 0, 7, &WEEKDAYS.map(|d| d.to_lowercase()))?;
+        // Both 0 and 7 are Sunday
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+        days_of_week.truncate(7);
+
+        Ok(CronExpr {
+            minutes: parse_field(fields[0], 0, 59, &[])?,
+            hours: parse_field(fields[1], 0, 23, &[])?,
+            days_of_month: parse_field(fields[2], 1, 31, &[])?,
+            months: parse_field(fields[3], 1, 12, &MONTHS.map(String::from))?,
+            days_of_week,
+            days_of_month_restricted: !fields[2].starts_with('*'),
+            days_of_week_restricted: !fields[4].starts_with('*'),
+        })
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if !self.months[date.month() as usize] {
+            return false;
+        }
+        let day_of_month = self.days_of_month[date.day() as usize];
+        let day_of_week = self.days_of_week[date.weekday().num_days_from_sunday() as usize];
+        if self.days_of_month_restricted && self.days_of_week_restricted {
+            day_of_month || day_of_week
+        } else {
+            day_of_month && day_of_week
+        }
+    }
+
+    // The first matching minute after `after`, looking up to five years ahead
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + chrono::Duration::minutes(1);
+        let mut date = start.date();
+
+        for _ in 0..366 * 5 {
+            if self.matches_date(date) {
+                let first = if date == start.date() { start.time() } else { NaiveTime::MIN };
+                for hour in first.hour()..24 {
+                    if !self.hours[hour as usize] {
+                        continue;
+                    }
+                    let first_minute = if hour == first.hour() { first.minute() } else { 0 };
+                    for minute in first_minute..60 {
+                        if !self.minutes[minute as usize] {
+                            continue;
+                        }
+                        // Times skipped by a DST change don't exist, move on to the next match
+                        if let Some(time) = Local.from_local_datetime(&date.and_hms_opt(hour, minute, 0)?).earliest() {
+                            return Some(time);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+
+        None
+    }
+
+    // systemd OnCalendar= values with the same meaning. systemd requires both day fields to
+    // match, so cron's "either day" case becomes two entries.
+    pub fn on_calendar(&self) -> Vec<String> {
+        let minutes = calendar_values(&self.minutes, 0);
+        let hours = calendar_values(&self.hours, 0);
+        let months = calendar_values(&self.months, 1);
+        let days_of_month = calendar_values(&self.days_of_month, 1);
+        let days_of_week: Vec<&str> = WEEKDAYS
+            .iter()
+            .zip(&self.days_of_week)
+            .filter(|(_, allowed)| **allowed)
+            .map(|(day, _)| *day)
+            .collect();
+
+        let entry = |weekdays: &str, days: &str| {
+            let weekdays = if weekdays.is_empty() { String::new() } else { format!("{} ", weekdays) };
+            format!("{}*-{}-{} {}:{}:00", weekdays, months, days, hours, minutes)
+        };
+
+        let weekdays = if days_of_week.len() == 7 { String::new() } else { days_of_week.join(",") };
+
+        if self.days_of_month_restricted && self.days_of_week_restricted {
+            vec![entry("", &days_of_month), entry(&weekdays, "*")]
+        } else {
+            vec![entry(&weekdays, &days_of_month)]
+        }
+    }
+}
+
+// Parse one cron field: "*", "5", "1-5", "*/15", "10-50/10", "mon-fri" and comma separated lists of those
+fn parse_field(field: &str, min: u32, max: u32, names: &[String]) -> Result<Vec<bool>, String> {
+    let mut allowed = vec![false; max as usize + 1];
+    let invalid = || format!("'{}' is not a valid cron field", field);
+
+    let value = |s: &str| -> Result<u32, String> {
+        let s = s.to_lowercase();
+        let value = match names.iter().position(|name| *name == s) {
+            Some(index) => index as u32 + min,
+            None => s.parse().map_err(|_| invalid())?,
+        };
+        if value < min || value > max {
+            return Err(format!("{} is out of range {}-{} in '{}'", value, min, max, field));
+        }
+        Ok(value)
+    };
+
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (item, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+
+        let (from, to) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((from, to)) => (value(from)?, value(to)?),
+                // "5/15" runs from 5 to the end of the range
+                None if step > 1 => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            },
+        };
+        if from > to {
+            return Err(invalid());
+        }
+
+        for v in (from..=to).step_by(step as usize) {
+            allowed[v as usize] = true;
+        }
+    }
+
+    Ok(allowed)
+}
+
+// "*" when every value is allowed, otherwise a comma separated list
+fn calendar_values(allowed: &[bool], min: usize) -> String {
+    if allowed[min..].iter().all(|a| *a) {
+        return "*".to_string();
+    }
+    allowed
+        .iter()
+        .enumerate()
+        .skip(min)
+        .filter(|(_, a)| **a)
+        .map(|(v, _)| format!("{:02}", v))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// Next run of each scheduled job, None when its schedule never fires
+type NextRun = (Schedule, Option<DateTime<Local>>);
+static NEXT_RUNS: LazyLock<Mutex<HashMap<String, NextRun>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Serialize)]
+pub struct ScheduledJob {
+    pub job_id: String,
+    pub schedule: Schedule,
+    // Unix timestamp
+    pub next_run: Option<i64>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct JobFinished {
+    pub job_id: String,
+    pub scheduled: bool,
+    pub success: bool,
+    pub message: String,
+}
+
+pub fn scheduled_jobs() -> Vec<ScheduledJob> {
+    let next_runs = NEXT_RUNS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut scheduled: Vec<ScheduledJob> = next_runs
+        .iter()
+        .map(|(job_id, (schedule, next_run))| ScheduledJob {
+            job_id: job_id.clone(),
+            schedule: schedule.clone(),
+            next_run: next_run.map(|t| t.timestamp()),
+        })
+        .collect();
+    scheduled.sort_by_key(|job| job.next_run.unwrap_or(i64::MAX));
+    scheduled
+}
+
+// Run scheduled jobs in the background for as long as the app is open
+pub fn start(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tick(&app_handle);
+            tokio::time::sleep(TICK).await;
+        }
+    });
+}
+
+fn tick(app_handle: &AppHandle) {
+    let jobs = match jobs::list() {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            eprintln!("Scheduler could not load jobs: {}", e);
+            return;
+        }
+    };
+
+    let now = Local::now();
+    let mut next_runs = NEXT_RUNS.lock().unwrap_or_else(|e| e.into_inner());
+    next_runs.retain(|job_id, _| jobs.iter().any(|job| job.id == *job_id && job.schedule.is_some()));
+
+    for job in jobs {
+        let Some(schedule) = job.schedule.clone() else {
+            continue;
+        };
+
+        // New or changed schedules start counting from now, a job never runs on app start
+        let due = match next_runs.get(&job.id) {
+            Some((known, due)) if *known == schedule => *due,
+            _ => {
+                next_runs.insert(job.id.clone(), (schedule.clone(), schedule.next_after(now)));
+                continue;
+            }
+        };
+
+        if due.is_some_and(|due| due <= now) {
+            next_runs.insert(job.id.clone(), (schedule.clone(), schedule.next_after(now)));
+            run_scheduled(app_handle.clone(), job);
+        }
+    }
+}
+
+fn run_scheduled(app_handle: AppHandle, job: Job) {
+    tauri::async_runtime::spawn(async move {
+        let result = match job.resolve_config_path(None) {
+            Ok(config_path) => {
+                jobs::run(&job, &config_path, None, "scheduled", false, |progress| {
+                    let _ = app_handle.emit("job-progress", progress);
+                })
+                .await
+            }
+            Err(e) => Err(e),
+        };
+
+        let finished = match result {
+            Ok(result) => JobFinished {
+                job_id: job.id.clone(),
+                scheduled: true,
+                success: result.success,
+                message: result.message,
+            },
+            Err(e) => {
+                eprintln!("Scheduled run of job '{}' failed: {}", job.name, e);
+                JobFinished {
+                    job_id: job.id.clone(),
+                    scheduled: true,
+                    success: false,
+                    message: e,
+                }
+            }
+        };
+        if let Err(e) = app_handle.emit("job-finished", finished) {
+            eprintln!("Failed to emit job result: {}", e);
+        }
+    });
+}
+
+// ~/.config/systemd/user
+fn systemd_user_dir() -> Result<PathBuf, String> {
+    dirs::config_dir()
+        .map(|dir| dir.join("systemd").join("user"))
+        .ok_or_else(|| "Could not determine the config directory".to_string())
+}
+
+fn unit_name(job: &Job) -> String {
+    format!("de_rclone-{}", job.id)
+}
+
+// Quote an ExecStart= argument, systemd expands % specifiers and $ variables otherwise
+fn systemd_quote(arg: &str) -> String {
+    let escaped = arg
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%")
+        .replace('$', "$$");
+    format!("\"{}\"", escaped)
+}
+
+// systemd wants an absolute path to the program
+fn find_rclone() -> Result<PathBuf, String> {
+    std::env::var_os("PATH")
+        .and_then(|path| {
+            std::env::split_paths(&path)
+                .map(|dir| dir.join("rclone"))
+                .find(|candidate| candidate.is_file())
+        })
+        .ok_or_else(|| "rclone was not found in PATH".to_string())
+}
+
+async fn systemctl(args: &[&str]) -> Result<(), String> {
+    let mut cmd = Command::new("systemctl");
+    cmd.arg("--user").args(args);
+
+    let output = runner::output("systemd", None, cmd).await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("systemctl --user {} failed: {}", args.join(" "), stderr.trim()));
+    }
+    Ok(())
+}
+
+// Write a user service and timer for a scheduled job and enable the timer, so the job also
+// runs while de_rclone is closed. The service uses the job's config, like scheduled runs in
+// the app. Returns the paths of the written units.
+pub async fn export_timer(job: &Job, config_path_opt: Option<String>) -> Result<(PathBuf, PathBuf), String> {
+    let config_path = job.resolve_config_path(config_path_opt)?;
+    let schedule = job
+        .schedule
+        .as_ref()
+        .ok_or_else(|| format!("Job '{}' has no schedule", job.name))?;
+
+    let timer_section = match schedule {
+        Schedule::Cron { expression } => CronExpr::parse(expression)?
+            .on_calendar()
+            .iter()
+            .map(|calendar| format!("OnCalendar={}\n", calendar))
+            .collect::<String>()
+            + "Persistent=true\n",
+        Schedule::Interval { seconds } => format!("OnBootSec={}s\nOnUnitActiveSec={}s\n", seconds, seconds),
+    };
+
+    let rclone = find_rclone()?;
+    let exec_start = std::iter::once(rclone.to_string_lossy().to_string())
+        .chain(jobs::args(job, &config_path)?)
+        .map(|arg| systemd_quote(&arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let service = format!(
+        "[Unit]\nDescription=de_rclone job: {}\n\n[Service]\nType=oneshot\nExecStart={}\n",
+        job.name, exec_start
+    );
+    let timer = format!(
+        "[Unit]\nDescription=Schedule for de_rclone job: {}\n\n[Timer]\n{}\n[Install]\nWantedBy=timers.target\n",
+        job.name, timer_section
+    );
+
+    let unit_dir = systemd_user_dir()?;
+    fs::create_dir_all(&unit_dir)
+        .map_err(|e| format!("Failed to create {}: {}", unit_dir.display(), e))?;
+
+    let name = unit_name(job);
+    let service_path = unit_dir.join(format!("{}.service", name));
+    let timer_path = unit_dir.join(format!("{}.timer", name));
+    fs::write(&service_path, service)
+        .map_err(|e| format!("Failed to write {}: {}", service_path.display(), e))?;
+    fs::write(&timer_path, timer)
+        .map_err(|e| format!("Failed to write {}: {}", timer_path.display(), e))?;
+
+    systemctl(&["daemon-reload"]).await?;
+    systemctl(&["enable", "--now", &format!("{}.timer", name)]).await?;
+
+    Ok((service_path, timer_path))
+}
+
+// Disable and delete the systemd units of a job, missing units are not an error
+pub async fn remove_timer(job: &Job) -> Result<(), String> {
+    let name = unit_name(job);
+    let unit_dir = systemd_user_dir()?;
+    let timer_path = unit_dir.join(format!("{}.timer", name));
+    let service_path = unit_dir.join(format!("{}.service", name));
+
+    if !timer_path.exists() && !service_path.exists() {
+        return Ok(());
+    }
+
+    if timer_path.exists() {
+        systemctl(&["disable", "--now", &format!("{}.timer", name)]).await?;
+    }
+    for path in [&timer_path, &service_path] {
+        if path.exists() {
+            fs::remove_file(path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        }
+    }
+
+    systemctl(&["daemon-reload"]).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for expression in ["", "* * * *", "60 * * * *", "* 24 * * *", "* * 0 * *", "*/0 * * * *", "5-1 * * * *", "* * * foo *"] {
+            assert!(CronExpr::parse(expression).is_err(), "{:?} accepted", expression);
+        }
+    }
+
+    #[test]
+    fn finds_the_next_matching_minute() {
+        let cron = CronExpr::parse("*/15 9-17 * * mon-fri").unwrap();
+        // 2024-01-05 is a Friday
+        assert_eq!(cron.next_after(at(2024, 1, 5, 9, 0)), Some(at(2024, 1, 5, 9, 15)));
+        assert_eq!(cron.next_after(at(2024, 1, 5, 17, 45)), Some(at(2024, 1, 8, 9, 0)));
+
+        let daily = CronExpr::parse("@daily").unwrap();
+        assert_eq!(daily.next_after(at(2024, 12, 31, 23, 59)), Some(at(2025, 1, 1, 0, 0)));
+    }
+
+    #[test]
+    fn runs_on_either_day_field_when_both_are_restricted() {
+        // The 15th, or any Sunday (7 is Sunday too)
+        let cron = CronExpr::parse("0 12 15 * 7").unwrap();
+        assert_eq!(cron.next_after(at(2024, 1, 1, 0, 0)), Some(at(2024, 1, 7, 12, 0)));
+        assert_eq!(cron.next_after(at(2024, 1, 14, 12, 0)), Some(at(2024, 1, 15, 12, 0)));
+    }
+
+    #[test]
+    fn never_matching_expressions_have_no_next_run() {
+        assert!(CronExpr::parse("0 0 30 2 *").unwrap().next_after(at(2024, 1, 1, 0, 0)).is_none());
+    }
+
+    #[test]
+    fn converts_to_on_calendar() {
+        assert_eq!(
+            CronExpr::parse("*/15 9-11 * * mon-fri").unwrap().on_calendar(),
+            vec!["Mon,Tue,Wed,Thu,Fri *-*-* 09,10,11:00,15,30,45:00"]
+        );
+        assert_eq!(CronExpr::parse("@monthly").unwrap().on_calendar(), vec!["*-*-01 00:00:00"]);
+        assert_eq!(
+            CronExpr::parse("30 2 1 jan sun").unwrap().on_calendar(),
+            vec!["*-01-01 02:30:00", "Sun *-01-* 02:30:00"]
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::bandwidth::BandwidthSettings;
+use crate::profiles::ProfileList;
+use crate::rc::RcEndpoint;
+use crate::rcd::BackendKind;
+use crate::runner::Timeouts;
+
+// Bump when the layout changes, an older de_rclone refuses files it can't read
+const SETTINGS_VERSION: u64 = 1;
+
+// The themes dist/ ships, the first is the default. Keep in sync with the theme select in app.js.
+pub const THEMES: &[&str] = &["cs16"];
+
+// Serializes read-modify-write cycles on the settings file
+static SETTINGS_LOCK: Mutex<()> = Mutex::new(());
+
+// Everything de_rclone remembers between sessions, except jobs and run history
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u64,
+    // One of THEMES
+    pub theme: String,
+    pub profiles: ProfileList,
+    pub bandwidth: BandwidthSettings,
+    pub timeouts: Timeouts,
+    // Changed through set_backend and attach_rcd, restored on startup
+    pub backend: BackendSettings,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct BackendSettings {
+    pub kind: BackendKind,
+    // The rcd to attach to when `kind` is "attached"
+    pub attach: Option<RcEndpoint>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            version: SETTINGS_VERSION,
+            theme: THEMES[0].to_string(),
+            profiles: ProfileList::default(),
+            bandwidth: BandwidthSettings::default(),
+            timeouts: Timeouts::default(),
+            backend: BackendSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !THEMES.contains(&self.theme.as_str()) {
+            return Err(format!("'{}' is not a theme, use one of: {}", self.theme, THEMES.join(", ")));
+        }
+        for profile in &self.profiles.profiles {
+            profile.validate()?;
+        }
+        self.bandwidth.validate()
+    }
+}
+
+fn config_dir() -> Result<PathBuf, String> {
+    dirs::config_dir()
+        .map(|dir| dir.join("de_rclone"))
+        .ok_or_else(|| "Could not determine the config directory".to_string())
+}
+
+// $XDG_CONFIG_HOME/de_rclone/settings.json, readable only by the user since it can hold
+// the credentials of an attached rcd
+fn settings_path() -> Result<PathBuf, String> {
+    Ok(config_dir()?.join("settings.json"))
+}
+
+pub fn load() -> Result<Settings, String> {
+    let _guard = SETTINGS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    read()
+}
+
+// Change the settings under the lock and save them
+pub fn modify<T>(change: impl FnOnce(&mut Settings) -> Result<T, String>) -> Result<T, String> {
+    let _guard = SETTINGS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut settings = read()?;
+    let result = change(&mut settings)?;
+    settings.validate()?;
+    write(&settings)?;
+    Ok(result)
+}
+
+fn read() -> Result<Settings, String> {
+    let path = settings_path()?;
+    if !path.exists() {
+        return Ok(Settings::default());
+    }
+
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let settings: Settings = serde_json::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    if settings.version > SETTINGS_VERSION {
+        return Err(format!("{} was written by a newer de_rclone (version {})", path.display(), settings.version));
+    }
+    Ok(settings)
+}
+
+fn write(settings: &Settings) -> Result<(), String> {
+    let path = settings_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let content = serde_json::to_string_pretty(settings).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+    let tmp_path = path.with_extension("json.tmp");
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp_path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+    fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_shipped_themes() {
+        let mut settings = Settings::default();
+        assert!(settings.validate().is_ok());
+
+        settings.theme = "dark".to_string();
+        let err = settings.validate().unwrap_err();
+        assert!(err.contains("cs16"), "{}", err);
+    }
+}
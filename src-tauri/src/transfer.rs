@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+// Flags that make rclone log JSON lines to stderr with a stats line every second
+pub const JSON_LOG_FLAGS: &[&str] = &["--use-json-log", "--stats", "1s", "--stats-log-level", "NOTICE"];
+
+// A file currently being transferred, from the "transferring" list of the stats
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default, rename_all(deserialize = "camelCase"))]
+pub struct FileTransfer {
+    pub name: String,
+    // -1 when the size is not known up front
+    pub size: i64,
+    pub bytes: u64,
+    pub percentage: u32,
+    // Bytes per second
+    pub speed: f64,
+    pub speed_avg: f64,
+    // Seconds left, None when rclone can't tell yet
+    pub eta: Option<u64>,
+}
+
+// Progress of a whole transfer as reported by `--stats`, see `rclone rc core/stats`
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default, rename_all(deserialize = "camelCase"))]
+pub struct TransferStats {
+    pub bytes: u64,
+    pub total_bytes: u64,
+    pub speed: f64,
+    pub eta: Option<u64>,
+    // Seconds since the transfer started
+    pub elapsed_time: f64,
+    pub checks: u64,
+    pub total_checks: u64,
+    pub transfers: u64,
+    pub total_transfers: u64,
+    pub deletes: u64,
+    pub renames: u64,
+    pub errors: u64,
+    pub fatal_error: bool,
+    pub retry_error: bool,
+    pub transferring: Vec<FileTransfer>,
+}
+
+// One line of `--use-json-log` output
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct LogEntry {
+    pub level: String,
+    pub msg: String,
+    // The file the message is about, if any
+    pub object: String,
+    // Set on `--dry-run` lines, e.g. "copy", "delete"
+    pub skipped: String,
+    pub size: Option<i64>,
+    pub stats: Option<TransferStats>,
+}
+
+// Parse a stderr line, None for anything that isn't a JSON log entry
+pub fn parse_log_line(line: &str) -> Option<LogEntry> {
+    let line = line.trim();
+    if !line.starts_with('{') {
+        return None;
+    }
+    serde_json::from_str(line).ok()
+}
+
+// The human readable part of a JSON log, leaving out the periodic stats
+pub fn log_messages(stderr: &str) -> Vec<String> {
+    stderr
+        .lines()
+        .filter_map(|line| match parse_log_line(line) {
+            Some(entry) if entry.stats.is_some() => None,
+            Some(entry) if entry.object.is_empty() => Some(format!("{}: {}", entry.level, entry.msg.trim())),
+            Some(entry) => Some(format!("{}: {}: {}", entry.level, entry.object, entry.msg.trim())),
+            None if line.trim().is_empty() => None,
+            None => Some(line.to_string()),
+        })
+        .collect()
+}
+
+// Messages logged at error level or above
+pub fn error_messages(stderr: &str) -> Vec<String> {
+    stderr
+        .lines()
+        .filter_map(parse_log_line)
+        .filter(|entry| entry.level == "error" || entry.level == "critical")
+        .map(|entry| {
+            if entry.object.is_empty() {
+                entry.msg.trim().to_string()
+            } else {
+                format!("{}: {}", entry.object, entry.msg.trim())
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_json_log_lines_only() {
+        let entry = parse_log_line(r#"{"level":"error","msg":"Failed to copy: 403 Forbidden","object":"a.txt","source":"x"}"#).unwrap();
+        assert_eq!(entry.level, "error");
+        assert_eq!(entry.object, "a.txt");
+        assert!(entry.stats.is_none());
+
+        assert!(parse_log_line("2024/01/01 12:00:00 NOTICE: plain text").is_none());
+        assert!(parse_log_line("{not json").is_none());
+        assert!(parse_log_line("").is_none());
+    }
+
+    #[test]
+    fn parses_stats_lines() {
+        let line = r#"  {"level":"notice","msg":"stats","stats":{"bytes":512,"totalBytes":1024,"eta":null,"transferring":[{"name":"a.bin","size":-1,"bytes":512,"percentage":50,"speedAvg":2.5}]}}"#;
+        let stats = parse_log_line(line).unwrap().stats.unwrap();
+        assert_eq!((stats.bytes, stats.total_bytes, stats.eta), (512, 1024, None));
+        assert_eq!(stats.transferring[0].size, -1);
+        assert_eq!(stats.transferring[0].speed_avg, 2.5);
+    }
+
+    #[test]
+    fn keeps_errors_and_plain_lines_out_of_the_stats() {
+        let stderr = "{\"level\":\"notice\",\"msg\":\"x\",\"stats\":{}}\n{\"level\":\"error\",\"msg\":\"boom \",\"object\":\"a\"}\nplain\n\n";
+        assert_eq!(log_messages(stderr), vec!["error: a: boom", "plain"]);
+        assert_eq!(error_messages(stderr), vec!["a: boom"]);
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+use crate::{rcd, runner};
+
+// How long a usage result is reused before asking the remote again
+pub const DEFAULT_USAGE_TTL_SECS: u64 = 300;
+
+// Output of `rclone about --json`, fields are missing when the backend doesn't report them
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct RemoteUsage {
+    #[serde(default)]
+    pub total: Option<u64>,
+    #[serde(default)]
+    pub used: Option<u64>,
+    #[serde(default)]
+    pub free: Option<u64>,
+    #[serde(default)]
+    pub trashed: Option<u64>,
+    #[serde(default)]
+    pub other: Option<u64>,
+    #[serde(default)]
+    pub objects: Option<u64>,
+    // False for backends without a quota API, the numbers are all empty then
+    #[serde(default)]
+    pub supported: bool,
+    #[serde(default)]
+    pub fetched_at: u64,
+}
+
+// Cached per config file and remote
+type UsageKey = (PathBuf, String);
+static USAGE_CACHE: LazyLock<Mutex<HashMap<UsageKey, (Instant, RemoteUsage)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn cached(key: &UsageKey, ttl: Duration) -> Option<RemoteUsage> {
+    let cache = USAGE_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .get(key)
+        .filter(|(fetched, _)| fetched.elapsed() < ttl)
+        .map(|(_, usage)| usage.clone())
+}
+
+// Usage of one remote, from the cache when it is younger than `ttl`
+pub async fn remote_usage(remote_name: &str, config_path: &Path, ttl: Duration, operation_id: Option<String>) -> Result<RemoteUsage, String> {
+    let key = (config_path.to_path_buf(), remote_name.to_string());
+    if let Some(usage) = cached(&key, ttl) {
+        return Ok(usage);
+    }
+
+    // operations/about replies with the same fields as `rclone about --json`
+    let about = match rcd::active_for(config_path) {
+        Some(endpoint) => endpoint
+            .call("operations/about", serde_json::json!({ "fs": format!("{}:", remote_name) }), operation_id)
+            .await
+            .and_then(|reply| serde_json::from_value(reply).map_err(|e| format!("Failed to parse rc operations/about reply: {}", e))),
+        None => {
+            let mut cmd = Command::new("rclone");
+            cmd.arg("about")
+                .arg(format!("{}:", remote_name))
+                .arg("--json")
+                .arg("--config")
+                .arg(config_path);
+
+            let output = runner::output("usage", operation_id, cmd).await?;
+
+            if output.status.success() {
+                serde_json::from_slice(&output.stdout).map_err(|e| format!("Failed to parse rclone about output: {}", e))
+            } else {
+                Err(String::from_utf8_lossy(&output.stderr).to_string())
+            }
+        }
+    };
+
+    let usage = match about {
+        Ok(usage) => RemoteUsage {
+            supported: true,
+            ..usage
+        },
+        // Remember unsupported backends too, asking again won't change the answer
+        Err(e) if e.contains("doesn't support about") => RemoteUsage::default(),
+        Err(e) => return Err(format!("Failed to get usage for {}: {}", remote_name, e)),
+    };
+
+    let usage = RemoteUsage {
+        fetched_at: runner::now_secs(),
+        ..usage
+    };
+
+    let mut cache = USAGE_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache.insert(key, (Instant::now(), usage.clone()));
+
+    Ok(usage)
+}
+
+// Forget the cached usage of a remote, e.g. after files were added or deleted
+pub fn invalidate(remote_name: &str) {
+    let mut cache = USAGE_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache.retain(|(_, name), _| name != remote_name);
+}
+
+// Usage of several remotes at once, remotes that fail are left out
+pub async fn remotes_usage(remote_names: &[String], config_path: &Path, ttl: Duration) -> HashMap<String, RemoteUsage> {
+    let mut tasks = tokio::task::JoinSet::new();
+
+    for remote_name in remote_names {
+        let remote_name = remote_name.clone();
+        let config_path = config_path.to_path_buf();
+        tasks.spawn(async move {
+            let usage = remote_usage(&remote_name, &config_path, ttl, None).await;
+            (remote_name, usage)
+        });
+    }
+
+    let mut result = HashMap::new();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((remote_name, Ok(usage))) => {
+                result.insert(remote_name, usage);
+            }
+            Ok((remote_name, Err(e))) => eprintln!("Skipping usage of {}: {}", remote_name, e),
+            Err(e) => eprintln!("Usage task failed: {}", e),
+        }
+    }
+
+    result
+}
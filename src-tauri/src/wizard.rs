@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::process::Command;
+
+use crate::rclone_config::resolve_config_path;
+use crate::plugins::rclone_providers;
+use crate::runner;
+
+// A backend rclone can configure on its own, for picking one without a plugin
+#[derive(Serialize)]
+pub struct Backend {
+    pub name: String,
+    pub description: String,
+    pub prefix: String,
+}
+
+// One choice offered for a question
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct ConfigExample {
+    #[serde(default)]
+    pub value: String,
+    #[serde(default)]
+    pub help: String,
+}
+
+// The option rclone is asking about, as returned in the "Option" field of its JSON output
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct ConfigOption {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub help: String,
+    #[serde(default)]
+    pub default: serde_json::Value,
+    #[serde(default)]
+    pub examples: Option<Vec<ConfigExample>>,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub is_password: bool,
+    #[serde(default)]
+    pub r#type: String,
+    // Only one of the examples may be chosen
+    #[serde(default)]
+    pub exclusive: bool,
+    #[serde(default)]
+    pub advanced: bool,
+}
+
+// One step of `rclone config create/update --non-interactive`.
+// While `done` is false the UI renders `option` as a form field and sends the answer back
+// together with `state` to continue_remote_config.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct ConfigQuestion {
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub option: Option<ConfigOption>,
+    // Set when the previous answer was rejected, the same question is asked again
+    #[serde(default)]
+    pub error: String,
+    #[serde(default)]
+    pub result: String,
+    #[serde(default, skip_deserializing)]
+    pub done: bool,
+}
+
+pub async fn list_backends() -> Result<Vec<Backend>, String> {
+    let providers = rclone_providers().await?;
+
+    let mut backends: Vec<Backend> = providers
+        .iter()
+        .map(|p| {
+            let field = |key: &str| p.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+            Backend {
+                name: field("Name"),
+                description: field("Description"),
+                prefix: field("Prefix"),
+            }
+        })
+        .filter(|b| !b.name.is_empty())
+        .collect();
+
+    backends.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(backends)
+}
+
+// Create the remote with whatever parameters are already known and return rclone's first question
+pub async fn start(remote_name: &str, backend: &str, params: &HashMap<String, String>, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<ConfigQuestion, String> {
+    let mut cmd = Command::new("rclone");
+    cmd.args(["config", "create", remote_name, backend]);
+
+    // Sorted so the command line is stable in logs
+    let mut keys: Vec<&String> = params.keys().collect();
+    keys.sort();
+    for key in keys {
+        cmd.arg(format!("{}={}", key, params[key]));
+    }
+
+    cmd.arg("--non-interactive");
+    run(cmd, config_path_opt, operation_id).await
+}
+
+// Answer the current question and get the next one
+pub async fn answer(remote_name: &str, state: &str, result: &str, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<ConfigQuestion, String> {
+    let mut cmd = Command::new("rclone");
+    cmd.args(["config", "update", remote_name, "--continue", "--state", state, "--result", result, "--non-interactive"]);
+    run(cmd, config_path_opt, operation_id).await
+}
+
+// Drop a remote whose setup was abandoned half way - rclone writes the section on the first step
+pub async fn abort(remote_name: &str, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<(), String> {
+    let mut cmd = Command::new("rclone");
+    cmd.args(["config", "delete", remote_name]);
+
+    cmd.arg("--config").arg(resolve_config_path(config_path_opt)?);
+
+    let operation = runner::Operation::start("config", operation_id)?;
+    let output = operation.output(cmd).await?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(format!("Failed to remove remote: {}", stderr))
+    }
+}
+
+// Each step runs as its own operation, cancelling it leaves the half-configured remote for abort
+async fn run(mut cmd: Command, config_path_opt: Option<String>, operation_id: Option<String>) -> Result<ConfigQuestion, String> {
+    cmd.arg("--config").arg(resolve_config_path(config_path_opt)?);
+
+    let operation = runner::Operation::start("config", operation_id)?;
+    let output = operation.output(cmd).await?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("rclone config failed: {}", stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_question(&stdout)
+}
+
+// rclone may log before the JSON, so parse from the first '{'
+fn parse_question(output: &str) -> Result<ConfigQuestion, String> {
+    let start = output.find('{')
+        .ok_or_else(|| format!("Unexpected output from rclone config: {}", output.trim()))?;
+
+    let mut question: ConfigQuestion = serde_json::from_str(output[start..].trim())
+        .map_err(|e| format!("Failed to parse rclone config output: {}", e))?;
+
+    // An empty state means rclone has no more questions and the remote is saved
+    question.done = question.state.is_empty();
+    Ok(question)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_choice_question() {
+        let output = r#"2024/01/01 12:00:00 NOTICE: Config file not found, using defaults
+{
+	"State": "*oauth-islocal,teamdrive,,",
+	"Option": {
+		"Name": "config_is_local",
+		"Help": "Use web browser to automatically authenticate rclone with remote?",
+		"Default": true,
+		"Examples": [
+			{ "Value": "true", "Help": "Yes" },
+			{ "Value": "false", "Help": "No" }
+		],
+		"Required": false,
+		"IsPassword": false,
+		"Type": "bool",
+		"Exclusive": true
+	},
+	"Error": ""
+}"#;
+        let question = parse_question(output).unwrap();
+        assert!(!question.done);
+        assert_eq!(question.state, "*oauth-islocal,teamdrive,,");
+        let option = question.option.unwrap();
+        assert_eq!(option.name, "config_is_local");
+        assert!(option.exclusive);
+        let examples: Vec<String> = option.examples.unwrap().into_iter().map(|e| e.value).collect();
+        assert_eq!(examples, vec!["true", "false"]);
+    }
+
+    #[test]
+    fn parses_a_free_text_question_asked_again() {
+        let output = r#"{"State":"root_folder_id,,","Option":{"Name":"root_folder_id","Help":"ID of the root folder.","Default":"","Examples":null,"Type":"string"},"Error":"invalid id"}"#;
+        let question = parse_question(output).unwrap();
+        assert!(!question.done);
+        assert_eq!(question.error, "invalid id");
+        let option = question.option.unwrap();
+        assert!(option.examples.is_none());
+        assert!(!option.exclusive);
+        assert_eq!(option.r#type, "string");
+    }
+
+    #[test]
+    fn an_empty_state_ends_the_wizard() {
+        let question = parse_question(r#"{"State":"","Option":null,"Error":"","Result":"ok"}"#).unwrap();
+        assert!(question.done);
+        assert!(question.option.is_none());
+        assert_eq!(question.result, "ok");
+
+        assert!(parse_question("Error: unknown backend").is_err());
+    }
+}
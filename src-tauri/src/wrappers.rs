@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use crate::rclone_config::{join_upstreams, target_remote, ConfigSection};
+
+// Virtual backends that sit on top of other remotes
+pub const WRAPPER_TYPES: &[&str] = &["alias", "union", "combine", "chunker", "compress", "hasher"];
+
+// Backends that wrap exactly one "remote:path" in their `remote` option.
+// The others take a list in `upstreams`.
+const SINGLE_UPSTREAM_TYPES: &[&str] = &["alias", "chunker", "compress", "hasher"];
+
+// Build the rclone.conf options for a wrapper remote, checking every upstream exists
+pub fn build_options(
+    remote_name: &str,
+    remote_type: &str,
+    upstreams: &[String],
+    extra: &HashMap<String, String>,
+    sections: &[ConfigSection],
+) -> Result<Vec<(String, String)>, String> {
+    if !WRAPPER_TYPES.contains(&remote_type) {
+        return Err(format!("Unsupported wrapper type '{}', expected one of: {}", remote_type, WRAPPER_TYPES.join(", ")));
+    }
+
+    let upstreams: Vec<String> = upstreams
+        .iter()
+        .map(|u| u.trim().to_string())
+        .filter(|u| !u.is_empty())
+        .collect();
+
+    if upstreams.is_empty() {
+        return Err(format!("A {} remote needs at least one upstream", remote_type));
+    }
+
+    for upstream in &upstreams {
+        let target = if remote_type == "combine" {
+            // combine maps directory names to remotes: "dir=remote:path"
+            match upstream.split_once('=') {
+                Some((dir, target)) if !dir.is_empty() && !target.is_empty() => target,
+                _ => return Err(format!("combine upstream '{}' must look like dir=remote:path", upstream)),
+            }
+        } else {
+            upstream.as_str()
+        };
+
+        // Local paths are fine, remotes must exist
+        if let Some(name) = target_remote(target) {
+            if name == remote_name {
+                return Err(format!("Remote '{}' can't wrap itself", remote_name));
+            }
+            if !sections.iter().any(|s| s.name == name) {
+                return Err(format!("Upstream remote '{}' not found in config", name));
+            }
+        }
+    }
+
+    let mut options = Vec::new();
+    if SINGLE_UPSTREAM_TYPES.contains(&remote_type) {
+        if upstreams.len() != 1 {
+            return Err(format!("A {} remote wraps exactly one remote, got {}", remote_type, upstreams.len()));
+        }
+        options.push(("remote".to_string(), upstreams[0].clone()));
+    } else {
+        options.push(("upstreams".to_string(), join_upstreams(&upstreams)));
+    }
+
+    // Backend specific settings such as chunk_size or action_policy, sorted for a stable file
+    let mut keys: Vec<&String> = extra
+        .keys()
+        .filter(|k| !["type", "remote", "upstreams"].contains(&k.as_str()))
+        .collect();
+    keys.sort();
+    for key in keys {
+        options.push((key.clone(), extra[key].clone()));
+    }
+
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rclone_config::parse_sections;
+
+    fn sections() -> Vec<ConfigSection> {
+        parse_sections("[drive]\ntype = drive\n\n[s3]\ntype = s3\n")
+    }
+
+    fn upstreams(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn builds_combine_upstreams_from_dir_mappings() {
+        let options = build_options("all", "combine", &upstreams(&["docs=drive:Documents", " pics=s3:my photos "]), &HashMap::new(), &sections()).unwrap();
+        assert_eq!(options, vec![("upstreams".to_string(), r#"docs=drive:Documents "pics=s3:my photos""#.to_string())]);
+
+        let err = build_options("all", "combine", &upstreams(&["drive:Documents"]), &HashMap::new(), &sections()).unwrap_err();
+        assert!(err.contains("dir=remote:path"), "{}", err);
+        let err = build_options("all", "combine", &upstreams(&["docs=missing:x"]), &HashMap::new(), &sections()).unwrap_err();
+        assert!(err.contains("'missing' not found"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_a_remote_wrapping_itself() {
+        let err = build_options("drive", "union", &upstreams(&["s3:", "drive:backup"]), &HashMap::new(), &sections()).unwrap_err();
+        assert!(err.contains("can't wrap itself"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_missing_upstreams() {
+        let err = build_options("mirror", "union", &upstreams(&["drive:", "nowhere:"]), &HashMap::new(), &sections()).unwrap_err();
+        assert!(err.contains("'nowhere' not found"), "{}", err);
+
+        let err = build_options("mirror", "union", &upstreams(&["  "]), &HashMap::new(), &sections()).unwrap_err();
+        assert!(err.contains("at least one upstream"), "{}", err);
+
+        // Local paths aren't remotes and need no section
+        assert!(build_options("mirror", "union", &upstreams(&["/srv/data", "drive:"]), &HashMap::new(), &sections()).is_ok());
+    }
+
+    #[test]
+    fn single_upstream_types_take_exactly_one_remote() {
+        let err = build_options("big", "chunker", &upstreams(&["drive:", "s3:"]), &HashMap::new(), &sections()).unwrap_err();
+        assert!(err.contains("exactly one remote, got 2"), "{}", err);
+
+        let extra = HashMap::from([("chunk_size".to_string(), "1G".to_string()), ("remote".to_string(), "s3:".to_string())]);
+        let options = build_options("big", "chunker", &upstreams(&["drive:chunks"]), &extra, &sections()).unwrap();
+        assert_eq!(options, vec![("remote".to_string(), "drive:chunks".to_string()), ("chunk_size".to_string(), "1G".to_string())]);
+    }
+}